@@ -0,0 +1,156 @@
+// prints trade events found in Client.txt as JSON Lines, one `{"event", "payload"}` object per line
+// with the same names and versioned payloads the app emits, or replays recorded log into another file with original timing
+//
+//   trade-log follow <Client.txt> [--backfill MINUTES]
+//   trade-log parse [<Client.txt> | -]
+//   trade-log replay <recorded.txt> <Client.txt> [--speed X] [--from TIME] [--to TIME]
+use anyhow::{anyhow, Context};
+use serde::Serialize;
+use std::{
+    io::Read,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::channel,
+        Arc, Mutex,
+    },
+};
+use trade_core::ipc;
+use trade_core::log_watcher;
+use trade_core::model::line_time;
+use trade_core::model_actor::Versioned;
+use trade_core::replay::{self, ReplayOptions};
+use trade_core::{BackfillSettings, FileLineReader, LineFramer, Model, ModelError};
+
+const USAGE: &str = "usage:
+    trade-log follow <Client.txt> [--backfill MINUTES]
+    trade-log parse [<Client.txt> | -]
+    trade-log replay <recorded.txt> <Client.txt> [--speed X] [--from TIME] [--to TIME]
+
+TIME is in log format, e.g. \"2023/10/13 01:54:50\"";
+
+fn print_event<T: Serialize + ?Sized>(event: &str, payload: &T) {
+    let line = serde_json::json!({ "event": event, "payload": payload });
+    println!("{}", line);
+}
+
+// every event bumps the version by one, as it does in the model actor
+fn printer<T: Serialize + ?Sized>(
+    event: &'static str,
+    version: &Arc<AtomicU64>,
+) -> impl Fn(&T) + Send + 'static {
+    let version = Arc::clone(version);
+    move |data| {
+        let version = version.fetch_add(1, Ordering::SeqCst) + 1;
+        print_event(event, &Versioned { version, data });
+    }
+}
+
+// same events the app sends to its windows
+fn subscribe(model: &mut Model) {
+    let version = Arc::new(AtomicU64::new(0));
+    model.outgoing_subscribe(printer(ipc::NEW_OUTGOING_TRADE, &version));
+    model.incoming_subscribe(printer(ipc::NEW_INCOMING_TRADE, &version));
+    model.intent_subscribe(printer(ipc::OUTGOING_INTENT_UPDATED, &version));
+    model.closed_subscribe(printer::<str>(ipc::TRADE_CLOSED, &version));
+}
+
+fn read_lines(path: Option<&str>) -> anyhow::Result<Vec<String>> {
+    let mut data = vec![];
+    match path {
+        None | Some("-") => std::io::stdin().read_to_end(&mut data)?,
+        Some(p) => std::fs::File::open(p)
+            .with_context(|| format!("can't open {}", p))?
+            .read_to_end(&mut data)?,
+    };
+    let mut framer = LineFramer::default();
+    let mut lines = framer.push(&data);
+    lines.extend(framer.finish());
+    Ok(lines)
+}
+
+fn parse(path: Option<&str>) -> anyhow::Result<()> {
+    let mut model = Model::new();
+    subscribe(&mut model);
+    for l in read_lines(path)?.iter() {
+        if let Err(ModelError::ParseError(line)) = model.try_add(l) {
+            eprintln!("can't parse trade line: {}", line);
+        }
+    }
+    Ok(())
+}
+
+fn follow(path: &str, backfill_minutes: u64) -> anyhow::Result<()> {
+    let mut model = Model::new();
+    subscribe(&mut model);
+    let model = Arc::new(Mutex::new(model));
+    let backfill = BackfillSettings {
+        minutes: backfill_minutes,
+        ..Default::default()
+    };
+    let now = chrono::Local::now().naive_local();
+    let mut reader = FileLineReader::open_with_backfill(Arc::clone(&model), path, &backfill, now)
+        .with_context(|| format!("can't open {}", path))?;
+
+    let (tx, rx) = channel();
+    let _watcher = log_watcher::watch_log(tx, path)?;
+
+    for res in rx {
+        let paths = res.map_err(|e| anyhow!("file notify events fail: {:?}", e))?;
+        if !log_watcher::touches_log(&paths, path) {
+            continue;
+        }
+        if let Err(e) = reader.process_new_content() {
+            eprintln!("can't read {}: {}", path, e);
+        }
+    }
+    Ok(())
+}
+
+fn replay(recorded: &str, target: &str, opts: &[&str]) -> anyhow::Result<()> {
+    let mut options = ReplayOptions::default();
+    for pair in opts.chunks(2) {
+        let time = |s: &str| line_time(s).ok_or_else(|| anyhow!("bad time: {}", s));
+        match pair {
+            ["--speed", x] => {
+                options.speed = x.parse().with_context(|| format!("bad speed: {}", x))?
+            }
+            ["--from", t] => options.from = Some(time(t)?),
+            ["--to", t] => options.to = Some(time(t)?),
+            _ => return Err(anyhow!("{}", USAGE)),
+        }
+    }
+
+    let schedule = replay::schedule(&read_lines(Some(recorded))?, &options);
+    let mut out = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(target)
+        .with_context(|| format!("can't open {}", target))?;
+    replay::replay(schedule, &mut out, std::thread::sleep)?;
+    Ok(())
+}
+
+fn run(args: &[String]) -> anyhow::Result<()> {
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    match args.as_slice() {
+        ["parse"] => parse(None),
+        ["parse", path] => parse(Some(path)),
+        ["follow", path] => follow(path, 0),
+        ["follow", path, "--backfill", minutes] => follow(
+            path,
+            minutes
+                .parse()
+                .with_context(|| format!("bad backfill minutes: {}", minutes))?,
+        ),
+        ["replay", recorded, target, opts @ ..] => replay(recorded, target, opts),
+        _ => Err(anyhow!("{}", USAGE)),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    }
+}
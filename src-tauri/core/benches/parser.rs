@@ -0,0 +1,170 @@
+// throughput of line parsing over a large log, run with `cargo bench -p trade-core`
+//
+// a real log can be used instead of the generated one:
+//   TRADE_BENCH_LOG=/path/to/Client.txt cargo bench -p trade-core
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::time::{Duration, Instant};
+use trade_core::parser::{self, Line};
+use trade_core::{Model, TradeStatus};
+
+const GENERATED_LINES: usize = 200_000;
+const ROUNDS: usize = 5;
+
+// mix seen in a busy trading session, whispers are rare compared to everything else
+const NOISE: &[&str] = &[
+    "2023/10/13 01:54:40 1054460421 cffb0719 [INFO Client 30680] Connecting to instance server at 159.223.16.80:6112",
+    "2023/10/13 01:54:40 1054460437 cffb0719 [DEBUG Client 30680] Connect time to instance server was 47ms",
+    "2023/10/13 01:54:41 1054461001 cffb0719 [INFO Client 30680] : You have entered Aspirants' Plaza.",
+    "2023/10/13 01:54:42 1054462210 cffb0719 [DEBUG Client 30680] Got Instance Details from login server",
+    "2023/10/13 01:54:43 1054463300 cffb0719 [INFO Client 30680] #Global: WTS 6 link chest, pm me",
+    "2023/10/13 01:54:44 1054464100 cffb0719 [INFO Client 30680] $Trade: selling maps, @me for prices",
+    "2023/10/13 01:54:45 1054465200 cffb0719 [INFO Client 30680] : AsuraStormfist has joined the area.",
+    "2023/10/13 01:54:46 1054466300 cffb0719 [DEBUG Client 30680] [SHADER] Delay: OFF",
+];
+const WHISPERS: &[&str] = &[
+    r#"2023/10/13 01:54:50 1054470421 cffb0719 [INFO Client 30680] @From buyer{}: Hi, I would like to buy your Tabula Rasa Simple Robe listed for 8 chaos in Ancestor (stash tab "~price 8 chaos"; position: left 1, top 1)"#,
+    r#"2023/10/13 01:54:51 1054471421 cffb0719 [INFO Client 30680] @To seller{}: Hi, I would like to buy your Goldrim Leather Cap listed for 1 chaos in Ancestor (stash tab "~price 1 chaos"; position: left 1, top 1)"#,
+    "2023/10/13 01:54:53 1054473421 cffb0719 [INFO Client 30680] @From buyer{}: sure, invite me",
+    "2023/10/13 01:55:01 1054481421 cffb0719 [INFO Client 30680] : That character is not online.",
+];
+
+fn generated() -> Vec<String> {
+    (0..GENERATED_LINES)
+        .map(|i| {
+            if i % 100 == 99 {
+                let w = WHISPERS[(i / 100) % WHISPERS.len()];
+                // a few dozen players trading at a time
+                w.replace("{}", &((i / 400) % 50).to_string())
+            } else {
+                NOISE[i % NOISE.len()].to_string()
+            }
+        })
+        .collect()
+}
+
+fn sample() -> Vec<String> {
+    match std::env::var("TRADE_BENCH_LOG") {
+        Ok(p) => std::fs::read_to_string(&p)
+            .unwrap_or_else(|e| panic!("can't read {}: {}", p, e))
+            .lines()
+            .map(|l| l.to_string())
+            .collect(),
+        Err(_) => generated(),
+    }
+}
+
+// previous parser, kept here as the baseline: whisper regex on every line, then the
+// system reply regex, then templates one by one and the quality regex, allocating
+// where `Model::try_add` used to
+static TRADE_MSG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"@(?<type>(?:From|To)) (?<guild>(?:<.+>){0,1})\s*(?<char>\w+):"#).unwrap()
+});
+static SYSTEM_MSG: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?:^|\] ): (?<msg>.+)$"#).unwrap());
+static SYSTEM_REPLIES: &[(&str, TradeStatus)] = &[
+    ("That character is not online.", TradeStatus::Offline),
+    ("AFK mode is now ON", TradeStatus::Afk),
+    ("You cannot whisper", TradeStatus::Failed),
+    ("Your message was not sent", TradeStatus::Failed),
+];
+static ENG_QUALITY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"level (?<lvl>\d+) (?<quality>\d+)% (?<item>.*)"#).unwrap());
+static ENG_MSGS: Lazy<Vec<Regex>> = Lazy::new(|| {
+    vec![
+        Regex::new(r#"Hi, I would like to buy your (?<item>[\w\s,]+) listed for (?<cost>[\d\.]+) (?<currency>[\w-]+) in (?<league>\w+)"#).unwrap(),
+        Regex::new(r#"Hi, I would like to buy your (?<item>[\w\s,]+) in (?<league>\w+)"#).unwrap(),
+        Regex::new(r#"Hi, I'd like to buy your (?<item>[\w\s,]+) for my (?<item2>[\w\s]+) in (?<league>\w+)"#).unwrap(),
+    ]
+});
+
+// only known replies are copied
+fn system_reply(line: &str) -> Option<(TradeStatus, String)> {
+    let msg = SYSTEM_MSG.captures(line)?.name("msg")?.as_str().trim();
+    SYSTEM_REPLIES
+        .iter()
+        .find(|(prefix, _)| msg.starts_with(prefix))
+        .map(|(_, status)| (status.clone(), msg.to_string()))
+}
+
+fn legacy(line: &str) -> usize {
+    if !TRADE_MSG.is_match(line) {
+        return system_reply(line).map_or(0, |(_, msg)| msg.len());
+    }
+    let c = TRADE_MSG.captures(line).unwrap();
+    let guild = c.get(2).map(|e| {
+        e.as_str()
+            .trim_matches(|c| c == '<' || c == '>')
+            .to_string()
+    });
+    let char = c["char"].to_string();
+    let offer = ENG_MSGS.iter().find_map(|re| re.captures(line));
+    char.len()
+        + guild.map_or(0, |g| g.len())
+        + offer.map_or(0, |o| {
+            let quality = ENG_QUALITY.captures(line);
+            o["item"].to_string().len() + quality.map_or(0, |q| q["item"].to_string().len())
+        })
+}
+
+fn current(line: &str) -> usize {
+    match parser::parse(line) {
+        Line::Whisper(w) => {
+            w.player.len()
+                + w.guild
+                    .map_or(0, |g| g.trim_matches(|c| c == '<' || c == '>').len())
+                + parser::parse_offer(w.text)
+                    .map_or(0, |o| o.item.len() + o.quality_item.map_or(0, |q| q.len()))
+        }
+        Line::SystemReply(_, msg) => msg.len(),
+        Line::Other => 0,
+    }
+}
+
+fn model(lines: &[String]) -> usize {
+    let mut model = Model::new();
+    lines.iter().filter(|l| model.try_add(l).is_ok()).count()
+}
+
+// best of a few rounds, the first one also warms up lazily compiled regexes
+fn measure<F: FnMut() -> usize>(mut f: F) -> (Duration, usize) {
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            let r = f();
+            (start.elapsed(), r)
+        })
+        .min_by_key(|(d, _)| *d)
+        .unwrap()
+}
+
+fn report(name: &str, lines: usize, (took, result): (Duration, usize)) -> (f64, usize) {
+    let per_sec = lines as f64 / took.as_secs_f64();
+    println!(
+        "{:<8} {:>10.2} ms {:>14.0} lines/s  (checksum {})",
+        name,
+        took.as_secs_f64() * 1000.0,
+        per_sec,
+        result
+    );
+    (per_sec, result)
+}
+
+fn main() {
+    let lines = sample();
+    println!("{} lines", lines.len());
+
+    let (old, old_sum) = report(
+        "legacy",
+        lines.len(),
+        measure(|| lines.iter().map(|l| legacy(l)).sum()),
+    );
+    let (new, new_sum) = report(
+        "parser",
+        lines.len(),
+        measure(|| lines.iter().map(|l| current(l)).sum()),
+    );
+    report("model", lines.len(), measure(|| model(&lines)));
+    // both read the same out of every line, otherwise the comparison is meaningless
+    assert_eq!(old_sum, new_sum, "legacy and parser disagree");
+    println!("parser is {:.1}x faster than legacy", new / old);
+}
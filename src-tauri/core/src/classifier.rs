@@ -0,0 +1,269 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// what other side wants with a whisper sent after the trade message
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, TS)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum FollowUpIntent {
+    #[serde(rename_all = "camelCase")]
+    CounterOffer {
+        cost_number: Option<String>,
+        cost_currency: Option<String>,
+    },
+    Cancel,
+    Waiting,
+    Sold,
+    Question,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    CounterOffer,
+    Cancel,
+    Waiting,
+    Sold,
+}
+
+struct Rule {
+    kind: Kind,
+    keywords: &'static [&'static str],
+}
+
+// keyword sets of each language, kinds are checked in the order of `classify`
+static LANGUAGES: &[&[Rule]] = &[
+    // english
+    &[
+        Rule {
+            kind: Kind::Sold,
+            keywords: &[
+                "sold",
+                "already sold",
+                "not available",
+                "no longer available",
+            ],
+        },
+        Rule {
+            kind: Kind::Cancel,
+            keywords: &[
+                "nvm",
+                "nevermind",
+                "never mind",
+                "no thanks",
+                "cancel",
+                "found another",
+                "not interested",
+            ],
+        },
+        Rule {
+            kind: Kind::CounterOffer,
+            keywords: &[
+                "can you do",
+                "would you do",
+                "would you take",
+                "will you take",
+                "how about",
+                "lower",
+                "offer",
+            ],
+        },
+        Rule {
+            kind: Kind::Waiting,
+            keywords: &[
+                "omw",
+                "on my way",
+                "sec",
+                "1 sec",
+                "one sec",
+                "wait",
+                "brb",
+                "coming",
+                "in map",
+                "1 min",
+                "one min",
+            ],
+        },
+    ],
+    // russian
+    &[
+        Rule {
+            kind: Kind::Sold,
+            keywords: &["продано", "продал", "уже продан", "продан"],
+        },
+        Rule {
+            kind: Kind::Cancel,
+            keywords: &["неактуально", "не надо", "отмена", "передумал"],
+        },
+        Rule {
+            kind: Kind::CounterOffer,
+            keywords: &["отдашь за", "можно за", "скинешь", "уступишь"],
+        },
+        Rule {
+            kind: Kind::Waiting,
+            keywords: &["сек", "щас", "иду", "подожди", "минуту"],
+        },
+    ],
+    // german
+    &[
+        Rule {
+            kind: Kind::Sold,
+            keywords: &["verkauft", "schon weg"],
+        },
+        Rule {
+            kind: Kind::Cancel,
+            keywords: &["doch nicht", "abbrechen", "kein interesse"],
+        },
+        Rule {
+            kind: Kind::CounterOffer,
+            keywords: &["würdest du", "nimmst du", "geht auch"],
+        },
+        Rule {
+            kind: Kind::Waiting,
+            keywords: &["moment", "komme", "gleich", "warte"],
+        },
+    ],
+    // portuguese
+    &[
+        Rule {
+            kind: Kind::Sold,
+            keywords: &["vendido", "já vendi", "ja vendi"],
+        },
+        Rule {
+            kind: Kind::Cancel,
+            keywords: &["esquece", "cancela"],
+        },
+        Rule {
+            kind: Kind::CounterOffer,
+            keywords: &["faz por", "aceita"],
+        },
+        Rule {
+            kind: Kind::Waiting,
+            keywords: &["pera", "espera", "indo", "já vou", "ja vou"],
+        },
+    ],
+];
+
+// words turning a keyword after them in the same clause around, as in "not sold yet"
+static NEGATORS: &[&str] = &[
+    "not", "no", "isn't", "isnt", "wasn't", "wasnt", "aren't", "arent", "don't", "dont", "didn't",
+    "didnt", "haven't", "havent", "hasn't", "hasnt", "не", "нет", "nicht", "kein", "keine", "não",
+    "nao", "nem",
+];
+
+static PRICE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?<cost>\d+(?:[\.,]\d+)?)\s*(?<currency>[^\d\s\?\!\.,]+)?"#).unwrap()
+});
+
+// lowercased words separated by single spaces, padded so keywords match whole words only
+fn normalize(msg: &str) -> String {
+    let words: Vec<String> = msg
+        .split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    format!(" {} ", words.join(" "))
+}
+
+// clauses of a message normalized one by one, negation doesn't reach past them
+fn clauses(msg: &str) -> Vec<String> {
+    msg.split([',', '.', ';', '!', '?'])
+        .map(normalize)
+        .collect()
+}
+
+// keyword is in the clause and not negated by a word in front of it
+fn affirmed(clause: &str, keyword: &str) -> bool {
+    clause
+        .match_indices(&format!(" {} ", keyword))
+        .any(|(i, _)| !clause[..i].split(' ').any(|w| NEGATORS.contains(&w)))
+}
+
+fn counter_offer(msg: &str) -> FollowUpIntent {
+    let matches = PRICE.captures(msg);
+    FollowUpIntent::CounterOffer {
+        cost_number: matches
+            .as_ref()
+            .and_then(|m| m.name("cost"))
+            .map(|m| m.as_str().replace(',', ".")),
+        cost_currency: matches
+            .as_ref()
+            .and_then(|m| m.name("currency"))
+            .map(|m| m.as_str().to_lowercase()),
+    }
+}
+
+pub fn classify(msg: &str) -> Option<FollowUpIntent> {
+    let clauses = clauses(msg);
+    for kind in [Kind::Sold, Kind::Cancel, Kind::CounterOffer, Kind::Waiting] {
+        let matched = LANGUAGES
+            .iter()
+            .flat_map(|rules| rules.iter())
+            .filter(|r| r.kind == kind)
+            .flat_map(|r| r.keywords.iter())
+            .any(|k| clauses.iter().any(|c| affirmed(c, k)));
+        if matched {
+            return Some(match kind {
+                Kind::CounterOffer => counter_offer(msg),
+                Kind::Cancel => FollowUpIntent::Cancel,
+                Kind::Waiting => FollowUpIntent::Waiting,
+                Kind::Sold => FollowUpIntent::Sold,
+            });
+        }
+    }
+
+    if msg.contains('?') {
+        return Some(FollowUpIntent::Question);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counter(cost: Option<&str>, currency: Option<&str>) -> FollowUpIntent {
+        FollowUpIntent::CounterOffer {
+            cost_number: cost.map(|s| s.to_string()),
+            cost_currency: currency.map(|s| s.to_string()),
+        }
+    }
+
+    #[test]
+    fn intents() {
+        let cases = [
+            ("are you there?", Some(FollowUpIntent::Question)),
+            ("can you do 3 div", Some(counter(Some("3"), Some("div")))),
+            (
+                "would you take 2.5 Divine?",
+                Some(counter(Some("2.5"), Some("divine"))),
+            ),
+            ("how about less", Some(counter(None, None))),
+            ("nvm", Some(FollowUpIntent::Cancel)),
+            ("Never mind, found another", Some(FollowUpIntent::Cancel)),
+            ("omw", Some(FollowUpIntent::Waiting)),
+            ("1 sec, finishing map", Some(FollowUpIntent::Waiting)),
+            ("sold sorry", Some(FollowUpIntent::Sold)),
+            ("уже продан", Some(FollowUpIntent::Sold)),
+            ("отдашь за 2 дива?", Some(counter(Some("2"), Some("дива")))),
+            ("moment bitte", Some(FollowUpIntent::Waiting)),
+            ("ok", None),
+            ("second", None),
+            // negated in the same clause only
+            ("not sold yet", None),
+            ("it isn't sold", None),
+            ("ainda não foi vendido", None),
+            ("noch nicht verkauft", None),
+            ("ещё не продано", None),
+            ("no, already sold", Some(FollowUpIntent::Sold)),
+            ("I'm not interested", Some(FollowUpIntent::Cancel)),
+            // ordinary chat
+            ("nm just mapping", None),
+            ("ist mir egal", None),
+            ("deixa eu ver", None),
+        ];
+        for (msg, expected) in cases {
+            assert_eq!(classify(msg), expected, "message: {}", msg);
+        }
+    }
+}
@@ -0,0 +1,460 @@
+use crate::commands::Dispatcher;
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use ts_rs::TS;
+
+pub trait Clock: Send {
+    fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct QueueSettings {
+    // minimal gap between two chat commands, game mutes players sending faster
+    #[serde(default = "default_min_interval_ms")]
+    pub min_interval_ms: u64,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+}
+
+fn default_min_interval_ms() -> u64 {
+    600
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+fn default_retry_delay_ms() -> u64 {
+    1000
+}
+
+impl Default for QueueSettings {
+    fn default() -> Self {
+        QueueSettings {
+            min_interval_ms: default_min_interval_ms(),
+            max_retries: default_max_retries(),
+            retry_delay_ms: default_retry_delay_ms(),
+        }
+    }
+}
+
+struct Job {
+    line: String,
+    // extra gap after previous command, used by macro steps
+    delay: Duration,
+    group: Option<String>,
+    attempts: u32,
+    retry_at: Option<Instant>,
+}
+
+// job taken off the queue while it is dispatched, it's too late to stop it once typing
+// started so cancelling only keeps it from being retried
+struct InFlight {
+    group: Option<String>,
+    cancelled: bool,
+}
+
+enum Next {
+    Wait(Option<Duration>),
+    Send(Job, Instant),
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueState {
+    pub pending: usize,
+    #[ts(type = "number")]
+    pub sent: u64,
+    #[ts(type = "number")]
+    pub failed: u64,
+    #[ts(type = "number")]
+    pub cancelled: u64,
+    pub next: Option<String>,
+}
+
+pub struct CommandQueue<C> {
+    settings: QueueSettings,
+    clock: C,
+    // only used by whoever runs the queue, it's locked on its own so the queue isn't
+    // locked while a command is typed
+    dispatcher: Arc<Mutex<Box<dyn Dispatcher>>>,
+    jobs: VecDeque<Job>,
+    in_flight: Option<InFlight>,
+    last_sent: Option<Instant>,
+    sent: u64,
+    failed: u64,
+    cancelled: u64,
+    state_callback: Box<dyn Fn(&QueueState) + Send>,
+}
+
+impl<C: Clock> CommandQueue<C> {
+    pub fn new(settings: QueueSettings, clock: C, dispatcher: Box<dyn Dispatcher>) -> Self {
+        CommandQueue {
+            settings,
+            clock,
+            dispatcher: Arc::new(Mutex::new(dispatcher)),
+            jobs: VecDeque::new(),
+            in_flight: None,
+            last_sent: None,
+            sent: 0,
+            failed: 0,
+            cancelled: 0,
+            state_callback: Box::new(|_| {}),
+        }
+    }
+
+    pub fn state_subscribe<F>(&mut self, cb: F)
+    where
+        F: Fn(&QueueState) + Send + 'static,
+    {
+        self.state_callback = Box::new(cb);
+    }
+
+    // steps are sent in order, each one waits its delay after the previous step
+    pub fn push_steps(&mut self, steps: Vec<(Duration, String)>, group: Option<String>) {
+        for (delay, line) in steps {
+            self.jobs.push_back(Job {
+                line,
+                delay,
+                group: group.clone(),
+                attempts: 0,
+                retry_at: None,
+            });
+        }
+        self.publish();
+    }
+
+    pub fn cancel_group(&mut self, group: &str) -> usize {
+        self.cancel_where(|g| g == Some(group))
+    }
+
+    pub fn cancel_all(&mut self) -> usize {
+        self.cancel_where(|_| true)
+    }
+
+    fn cancel_where<P: Fn(Option<&str>) -> bool>(&mut self, pred: P) -> usize {
+        let before = self.jobs.len();
+        self.jobs.retain(|j| !pred(j.group.as_deref()));
+        let removed = before - self.jobs.len();
+        let stopped = match self.in_flight.as_mut() {
+            Some(f) if !f.cancelled && pred(f.group.as_deref()) => {
+                f.cancelled = true;
+                1
+            }
+            _ => 0,
+        };
+        if removed > 0 {
+            self.cancelled += removed as u64;
+            self.publish();
+        }
+        removed + stopped
+    }
+
+    fn due_at(&self, job: &Job) -> Option<Instant> {
+        let gap = Duration::from_millis(self.settings.min_interval_ms).max(job.delay);
+        let paced = self.last_sent.map(|t| t + gap);
+        match (paced, job.retry_at) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    // sends every command that is due, returns time until the next one
+    pub fn tick(&mut self) -> Option<Duration> {
+        loop {
+            match self.next() {
+                Next::Wait(wait) => return wait,
+                Next::Send(job, now) => {
+                    let r = self.dispatcher.lock().unwrap().dispatch(&job.line);
+                    self.finish(job, now, r);
+                }
+            }
+        }
+    }
+
+    // takes the job that is due off the queue until `finish`
+    fn next(&mut self) -> Next {
+        let now = self.clock.now();
+        let job = match self.jobs.front() {
+            Some(j) => j,
+            None => return Next::Wait(None),
+        };
+        if let Some(due) = self.due_at(job) {
+            if due > now {
+                return Next::Wait(Some(due - now));
+            }
+        }
+        let job = self.jobs.pop_front().unwrap();
+        self.in_flight = Some(InFlight {
+            group: job.group.clone(),
+            cancelled: false,
+        });
+        Next::Send(job, now)
+    }
+
+    fn finish(&mut self, mut job: Job, now: Instant, result: anyhow::Result<()>) {
+        let cancelled = self.in_flight.take().is_some_and(|f| f.cancelled);
+        match result {
+            Ok(_) => {
+                debug!("chat command sent: {}", job.line);
+                self.sent += 1;
+                self.last_sent = Some(now);
+            }
+            Err(e) if cancelled => {
+                debug!("cancelled chat command {} failed: {}", job.line, e);
+                self.cancelled += 1;
+            }
+            Err(e) => {
+                job.attempts += 1;
+                if job.attempts > self.settings.max_retries {
+                    error!("chat command {} failed, giving up: {}", job.line, e);
+                    self.failed += 1;
+                } else {
+                    error!("chat command {} failed, retrying: {}", job.line, e);
+                    job.retry_at = Some(now + Duration::from_millis(self.settings.retry_delay_ms));
+                    self.jobs.push_front(job);
+                }
+            }
+        }
+        self.publish();
+    }
+
+    pub fn state(&self) -> QueueState {
+        QueueState {
+            pending: self.jobs.len(),
+            sent: self.sent,
+            failed: self.failed,
+            cancelled: self.cancelled,
+            next: self.jobs.front().map(|j| j.line.clone()),
+        }
+    }
+
+    fn publish(&self) {
+        (self.state_callback)(&self.state());
+    }
+}
+
+// runs queue on its own thread, every change to the queue wakes it up
+pub struct QueueHandle<C> {
+    queue: Arc<Mutex<CommandQueue<C>>>,
+    wake: Mutex<Sender<()>>,
+}
+
+impl<C: Clock + 'static> QueueHandle<C> {
+    pub fn spawn(queue: CommandQueue<C>) -> Self {
+        let queue = Arc::new(Mutex::new(queue));
+        let (tx, rx) = channel();
+        let q = Arc::clone(&queue);
+        std::thread::spawn(move || run(q, rx));
+        QueueHandle {
+            queue,
+            wake: Mutex::new(tx),
+        }
+    }
+
+    pub fn with<R, F: FnOnce(&mut CommandQueue<C>) -> R>(&self, f: F) -> R {
+        let r = f(&mut self.queue.lock().unwrap());
+        let _ = self.wake.lock().unwrap().send(());
+        r
+    }
+}
+
+// the queue stays unlocked while a command is typed, so commands of the app
+// and cancelling don't wait for it
+fn run<C: Clock>(queue: Arc<Mutex<CommandQueue<C>>>, rx: Receiver<()>) {
+    loop {
+        let wait = loop {
+            let (next, dispatcher) = {
+                let mut q = queue.lock().unwrap();
+                (q.next(), Arc::clone(&q.dispatcher))
+            };
+            match next {
+                Next::Wait(wait) => break wait,
+                Next::Send(job, now) => {
+                    let r = dispatcher.lock().unwrap().dispatch(&job.line);
+                    queue.lock().unwrap().finish(job, now, r);
+                }
+            }
+        };
+        let r = match wait {
+            Some(d) => rx.recv_timeout(d),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        if let Err(RecvTimeoutError::Disconnected) = r {
+            debug!("command queue stopped");
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Dispatcher;
+    use crate::test_utilities::{FakeClock, RecordingDispatcher};
+
+    fn queue(clock: &FakeClock, dispatcher: &RecordingDispatcher) -> CommandQueue<FakeClock> {
+        let settings = QueueSettings {
+            min_interval_ms: 500,
+            max_retries: 1,
+            retry_delay_ms: 1000,
+        };
+        CommandQueue::new(settings, clock.clone(), Box::new(dispatcher.clone()))
+    }
+
+    fn steps(lines: &[(u64, &str)]) -> Vec<(Duration, String)> {
+        lines
+            .iter()
+            .map(|(d, l)| (Duration::from_millis(*d), l.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn pacing() {
+        let clock = FakeClock::new();
+        let dispatcher = RecordingDispatcher::new();
+        let mut q = queue(&clock, &dispatcher);
+
+        q.push_steps(
+            steps(&[(0, "/invite a"), (0, "@a hi"), (800, "/kick a")]),
+            None,
+        );
+        assert_eq!(q.tick(), Some(Duration::from_millis(500)));
+        assert_eq!(dispatcher.lines(), vec!["/invite a"]);
+
+        clock.advance(Duration::from_millis(499));
+        assert_eq!(q.tick(), Some(Duration::from_millis(1)));
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(q.tick(), Some(Duration::from_millis(800)));
+        assert_eq!(dispatcher.lines(), vec!["/invite a", "@a hi"]);
+
+        clock.advance(Duration::from_millis(800));
+        assert_eq!(q.tick(), None);
+        assert_eq!(dispatcher.lines(), vec!["/invite a", "@a hi", "/kick a"]);
+        assert_eq!(q.state().sent, 3);
+    }
+
+    #[test]
+    fn retries() {
+        let clock = FakeClock::new();
+        let dispatcher = RecordingDispatcher::new();
+        let mut q = queue(&clock, &dispatcher);
+
+        dispatcher.fail_next(3);
+        q.push_steps(steps(&[(0, "/invite a"), (0, "/invite b")]), None);
+        assert_eq!(q.tick(), Some(Duration::from_millis(1000)));
+        clock.advance(Duration::from_millis(1000));
+        // second failure for the first command drops it, second command fails once
+        assert_eq!(q.tick(), Some(Duration::from_millis(1000)));
+        assert_eq!(q.state().failed, 1);
+        clock.advance(Duration::from_millis(1000));
+        assert_eq!(q.tick(), None);
+
+        assert_eq!(dispatcher.lines(), vec!["/invite b"]);
+        assert_eq!(q.state().sent, 1);
+    }
+
+    #[test]
+    fn cancellation_and_state() {
+        let clock = FakeClock::new();
+        let dispatcher = RecordingDispatcher::new();
+        let mut q = queue(&clock, &dispatcher);
+        let states = Arc::new(Mutex::new(vec![]));
+        {
+            let states = Arc::clone(&states);
+            q.state_subscribe(move |s| states.lock().unwrap().push(s.clone()));
+        }
+
+        q.push_steps(steps(&[(0, "/invite a"), (0, "@a hi")]), Some("a".into()));
+        q.push_steps(steps(&[(0, "/invite b")]), Some("b".into()));
+        assert_eq!(q.cancel_group("a"), 2);
+        assert_eq!(q.cancel_group("a"), 0);
+        assert_eq!(q.tick(), None);
+        assert_eq!(dispatcher.lines(), vec!["/invite b"]);
+
+        q.push_steps(steps(&[(0, "/invite c"), (0, "/invite d")]), None);
+        assert_eq!(q.cancel_all(), 2);
+
+        let last = states.lock().unwrap().last().cloned().unwrap();
+        assert_eq!(
+            last,
+            QueueState {
+                pending: 0,
+                sent: 1,
+                failed: 0,
+                cancelled: 4,
+                next: None,
+            }
+        );
+    }
+
+    // reports the line it got and fails once released
+    struct BlockingDispatcher {
+        started: Sender<String>,
+        release: Receiver<()>,
+    }
+
+    impl Dispatcher for BlockingDispatcher {
+        fn dispatch(&mut self, line: &str) -> anyhow::Result<()> {
+            self.started.send(line.to_string()).unwrap();
+            self.release.recv().unwrap();
+            anyhow::bail!("window lost focus")
+        }
+    }
+
+    #[test]
+    fn cancelled_while_dispatching() {
+        let (started_tx, started) = channel();
+        let (release, release_rx) = channel();
+        let dispatcher = BlockingDispatcher {
+            started: started_tx,
+            release: release_rx,
+        };
+        let settings = QueueSettings {
+            min_interval_ms: 500,
+            max_retries: 1,
+            retry_delay_ms: 1000,
+        };
+        let mut q = CommandQueue::new(settings, FakeClock::new(), Box::new(dispatcher));
+        let (states_tx, states) = channel();
+        q.state_subscribe(move |s| {
+            let _ = states_tx.send(s.clone());
+        });
+        let handle = QueueHandle::spawn(q);
+
+        handle.with(|q| q.push_steps(steps(&[(0, "/invite a")]), Some("a".into())));
+        assert_eq!(
+            started.recv_timeout(Duration::from_secs(5)).unwrap(),
+            "/invite a"
+        );
+        // queue can be changed while the command is typed
+        assert_eq!(handle.with(|q| q.cancel_group("a")), 1);
+        assert_eq!(handle.with(|q| q.cancel_group("a")), 0);
+        release.send(()).unwrap();
+
+        // failed command isn't retried
+        let last = states
+            .iter()
+            .find(|s| s.cancelled == 1)
+            .expect("cancelled state");
+        assert_eq!((last.pending, last.failed, last.sent), (0, 0, 0));
+    }
+}
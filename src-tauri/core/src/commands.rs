@@ -0,0 +1,199 @@
+use crate::model::{TradeInfo, TradeType};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use ts_rs::TS;
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum ChatCommand {
+    Invite,
+    Kick,
+    TradeWith,
+    Hideout,
+    Whisper(String),
+    Raw(String),
+}
+
+impl ChatCommand {
+    // renders command into chat line, `{player}`, `{item}`, `{price}` and `{league}`
+    // placeholders are substituted with trade values
+    pub fn render(&self, trade: &TradeInfo) -> String {
+        match self {
+            ChatCommand::Invite => format!("/invite {}", trade.player_name()),
+            ChatCommand::Kick => format!("/kick {}", trade.player_name()),
+            ChatCommand::TradeWith => format!("/tradewith {}", trade.player_name()),
+            ChatCommand::Hideout => format!("/hideout {}", trade.player_name()),
+            ChatCommand::Whisper(msg) => {
+                format!("@{} {}", trade.player_name(), substitute(msg, trade))
+            }
+            ChatCommand::Raw(line) => substitute(line, trade),
+        }
+    }
+}
+
+fn substitute(s: &str, trade: &TradeInfo) -> String {
+    s.replace("{player}", trade.player_name())
+        .replace("{item}", trade.item_name())
+        .replace("{price}", &trade.price())
+        .replace("{league}", trade.league())
+}
+
+// fixed actions behind overlay buttons and global hotkeys
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum TradeAction {
+    Invite,
+    Trade,
+    Kick,
+    Thank,
+    Hideout,
+    AskToWait,
+    StillInterested,
+    SoldAlready,
+}
+
+impl TradeAction {
+    pub fn commands(&self, trade_type: &TradeType) -> Vec<ChatCommand> {
+        let whisper = |s: &str| vec![ChatCommand::Whisper(s.to_string())];
+        match (self, trade_type) {
+            (TradeAction::Invite, _) => vec![ChatCommand::Invite],
+            (TradeAction::Trade, _) => vec![ChatCommand::TradeWith],
+            (TradeAction::Kick, _) => vec![ChatCommand::Kick],
+            (TradeAction::Hideout, _) => vec![ChatCommand::Hideout],
+            (TradeAction::Thank, TradeType::Incoming) => whisper("ty, gl"),
+            (TradeAction::Thank, TradeType::Outgoing) => whisper("ty"),
+            (TradeAction::AskToWait, _) => whisper("i'm busy right now, please wait a bit"),
+            (TradeAction::StillInterested, _) => whisper("are you still interested in {item}?"),
+            (TradeAction::SoldAlready, _) => whisper("sorry, {item} is already sold"),
+        }
+    }
+
+    // chat lines for the trade, ready to be queued
+    pub fn steps(&self, trade: &TradeInfo) -> Vec<(Duration, String)> {
+        self.commands(trade.trade_type())
+            .iter()
+            .map(|c| (Duration::ZERO, c.render(trade)))
+            .collect()
+    }
+}
+
+pub trait Dispatcher: Send {
+    fn dispatch(&mut self, line: &str) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryBackend {
+    // x11 when available, then wayland, otherwise only logs commands
+    Auto,
+    Log,
+    X11,
+    Wayland,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryMode {
+    Type,
+    Paste,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct DeliverySettings {
+    #[serde(default = "default_backend")]
+    pub backend: DeliveryBackend,
+    #[serde(default = "default_mode")]
+    pub mode: DeliveryMode,
+    #[serde(default = "default_window_title")]
+    pub window_title: String,
+    #[serde(default = "default_key_delay_ms")]
+    pub key_delay_ms: u64,
+    #[serde(default = "default_focus_delay_ms")]
+    pub focus_delay_ms: u64,
+}
+
+fn default_backend() -> DeliveryBackend {
+    DeliveryBackend::Auto
+}
+
+fn default_mode() -> DeliveryMode {
+    DeliveryMode::Type
+}
+
+fn default_window_title() -> String {
+    "Path of Exile".to_string()
+}
+
+fn default_key_delay_ms() -> u64 {
+    5
+}
+
+fn default_focus_delay_ms() -> u64 {
+    50
+}
+
+impl Default for DeliverySettings {
+    fn default() -> Self {
+        DeliverySettings {
+            backend: default_backend(),
+            mode: default_mode(),
+            window_title: default_window_title(),
+            key_delay_ms: default_key_delay_ms(),
+            focus_delay_ms: default_focus_delay_ms(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn make_dispatcher(settings: &DeliverySettings) -> Box<dyn Dispatcher> {
+    use crate::{wayland_dispatcher::WaylandDispatcher, x11_dispatcher::X11Dispatcher};
+
+    let x11 = || X11Dispatcher::connect(None, settings.clone());
+    match settings.backend {
+        DeliveryBackend::Log => Box::new(LogDispatcher),
+        DeliveryBackend::Wayland => Box::new(WaylandDispatcher::new(settings.clone())),
+        DeliveryBackend::X11 => match x11() {
+            Ok(d) => Box::new(d),
+            Err(e) => {
+                error!("can't init x11 chat delivery: {:?}", e);
+                Box::new(LogDispatcher)
+            }
+        },
+        DeliveryBackend::Auto => match x11() {
+            Ok(d) => Box::new(d),
+            Err(e) if std::env::var_os("WAYLAND_DISPLAY").is_some() => {
+                info!("x11 chat delivery unavailable, using wayland: {:?}", e);
+                Box::new(WaylandDispatcher::new(settings.clone()))
+            }
+            Err(e) => {
+                error!("no chat delivery backend available: {:?}", e);
+                Box::new(LogDispatcher)
+            }
+        },
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn make_dispatcher(settings: &DeliverySettings) -> Box<dyn Dispatcher> {
+    if !matches!(
+        settings.backend,
+        DeliveryBackend::Auto | DeliveryBackend::Log
+    ) {
+        error!(
+            "chat delivery backend {:?} is not supported on this platform",
+            settings.backend
+        );
+    }
+    Box::new(LogDispatcher)
+}
+
+// used until real delivery backend is configured, only logs chat lines
+pub struct LogDispatcher;
+
+impl Dispatcher for LogDispatcher {
+    fn dispatch(&mut self, line: &str) -> anyhow::Result<()> {
+        info!("dispatch chat command: {}", line);
+        Ok(())
+    }
+}
@@ -0,0 +1,298 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::{
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+use ts_rs::TS;
+
+const GAME_DIRS: &[&str] = &["Path of Exile", "Path of Exile 2"];
+const STANDALONE_DIR: &str = "Grinding Gear Games";
+
+#[derive(Debug, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum CandidateSource {
+    Standalone,
+    Steam,
+    Proton,
+    Wine,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct Candidate {
+    pub path: String,
+    pub source: CandidateSource,
+    // milliseconds since unix epoch
+    #[ts(type = "number | null")]
+    pub modified: Option<u64>,
+}
+
+// places to look in, filled from environment by `Roots::from_env`
+#[derive(Debug, Default, Clone)]
+pub struct Roots {
+    pub home: Option<PathBuf>,
+    // program files directories on windows
+    pub program_files: Vec<PathBuf>,
+    pub steam: Vec<PathBuf>,
+}
+
+impl Roots {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var_os(name).map(PathBuf::from);
+        let home = var("HOME").or_else(|| var("USERPROFILE"));
+        let program_files: Vec<PathBuf> = ["ProgramFiles(x86)", "ProgramFiles"]
+            .iter()
+            .filter_map(|v| var(v))
+            .collect();
+
+        let mut steam: Vec<PathBuf> = program_files.iter().map(|p| p.join("Steam")).collect();
+        if let Some(home) = home.as_ref() {
+            for dir in [
+                ".steam/steam",
+                ".steam/root",
+                ".local/share/Steam",
+                // flatpak steam
+                ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+                "Library/Application Support/Steam",
+            ] {
+                steam.push(home.join(dir));
+            }
+        }
+        Roots {
+            home,
+            program_files,
+            steam,
+        }
+    }
+}
+
+static VDF_PATH: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?m)^\s*"(?:path|\d+)"[ \t]+"(?<path>(?:[^"\\]|\\.)+)""#).unwrap());
+
+// library folders of steam, both the current and the old numbered format
+pub fn library_folders(vdf: &str) -> Vec<PathBuf> {
+    VDF_PATH
+        .captures_iter(vdf)
+        .map(|c| c["path"].replace("\\\\", "\\"))
+        // installed app sizes look the same as numbered folders of old format
+        .filter(|p| !p.chars().all(|c| c.is_ascii_digit()))
+        .map(PathBuf::from)
+        .collect()
+}
+
+fn game_logs(base: &Path) -> Vec<PathBuf> {
+    GAME_DIRS
+        .iter()
+        .map(|g| base.join(g).join("logs").join("Client.txt"))
+        .collect()
+}
+
+// standalone install inside of windows drive of a wine prefix
+fn prefix_logs(prefix: &Path) -> Vec<PathBuf> {
+    ["Program Files (x86)", "Program Files"]
+        .iter()
+        .flat_map(|p| game_logs(&prefix.join("drive_c").join(p).join(STANDALONE_DIR)))
+        .collect()
+}
+
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|rd| {
+            rd.filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn libraries(steam: &Path) -> Vec<PathBuf> {
+    let mut libs = vec![steam.to_path_buf()];
+    for vdf in ["steamapps/libraryfolders.vdf", "config/libraryfolders.vdf"] {
+        if let Ok(s) = std::fs::read_to_string(steam.join(vdf)) {
+            libs.extend(library_folders(&s));
+        }
+    }
+    libs
+}
+
+fn possible_paths(roots: &Roots) -> Vec<(PathBuf, CandidateSource)> {
+    let mut paths = vec![];
+    for pf in roots.program_files.iter() {
+        for p in game_logs(&pf.join(STANDALONE_DIR)) {
+            paths.push((p, CandidateSource::Standalone));
+        }
+    }
+
+    for steam in roots.steam.iter() {
+        for lib in libraries(steam) {
+            let steamapps = lib.join("steamapps");
+            for p in game_logs(&steamapps.join("common")) {
+                paths.push((p, CandidateSource::Steam));
+            }
+            // standalone client added to steam as non-steam game runs in its own proton prefix
+            for app in subdirs(&steamapps.join("compatdata")) {
+                for p in prefix_logs(&app.join("pfx")) {
+                    paths.push((p, CandidateSource::Proton));
+                }
+            }
+        }
+    }
+
+    if let Some(home) = roots.home.as_ref() {
+        let mut prefixes = vec![home.join(".wine")];
+        // lutris keeps a prefix per game
+        prefixes.extend(subdirs(&home.join("Games")));
+        for prefix in prefixes {
+            for p in prefix_logs(&prefix) {
+                paths.push((p, CandidateSource::Wine));
+            }
+        }
+    }
+    paths
+}
+
+// existing logs, the most recently written first
+pub fn discover_in(roots: &Roots) -> Vec<Candidate> {
+    let mut found: Vec<(PathBuf, Candidate)> = vec![];
+    for (path, source) in possible_paths(roots) {
+        let meta = match std::fs::metadata(&path) {
+            Ok(m) if m.is_file() => m,
+            _ => continue,
+        };
+        // the same library is often reachable through several steam symlinks
+        let real = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if found.iter().any(|(r, _)| *r == real) {
+            continue;
+        }
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64);
+        found.push((
+            real,
+            Candidate {
+                path: path.to_string_lossy().into_owned(),
+                source,
+                modified,
+            },
+        ));
+    }
+
+    let mut candidates: Vec<Candidate> = found.into_iter().map(|(_, c)| c).collect();
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.modified));
+    candidates
+}
+
+pub fn discover() -> Vec<Candidate> {
+    discover_in(&Roots::from_env())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vdf_formats() {
+        let current = r#"
+"libraryfolders"
+{
+	"0"
+	{
+		"path"		"/home/user/.local/share/Steam"
+		"label"		""
+		"apps"
+		{
+			"238960"		"44116471392"
+		}
+	}
+	"1"
+	{
+		"path"		"D:\\SteamLibrary"
+	}
+}"#;
+        assert_eq!(
+            library_folders(current),
+            vec![
+                PathBuf::from("/home/user/.local/share/Steam"),
+                PathBuf::from("D:\\SteamLibrary")
+            ]
+        );
+
+        let old = r#"
+"LibraryFolders"
+{
+	"TimeNextStatsReport"		"1561832478"
+	"ContentStatsID"		"-158337411"
+	"1"		"E:\\Games\\Steam"
+}"#;
+        assert_eq!(
+            library_folders(old),
+            vec![PathBuf::from("E:\\Games\\Steam")]
+        );
+    }
+
+    // files are written one after another so their modification times differ
+    fn touch(path: &Path) {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, b"").unwrap();
+    }
+
+    #[test]
+    fn ranked_candidates() {
+        let root = std::env::temp_dir().join(format!("discovery-{}", uuid::Uuid::new_v4()));
+        let home = root.join("home");
+        let steam = home.join(".local/share/Steam");
+        let library = root.join("library");
+        let pf = root.join("pf");
+
+        std::fs::create_dir_all(steam.join("steamapps")).unwrap();
+        std::fs::write(
+            steam.join("steamapps/libraryfolders.vdf"),
+            format!(
+                "\"libraryfolders\"\n{{\n\t\"0\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}",
+                library.display()
+            ),
+        )
+        .unwrap();
+
+        let steam_log = library.join("steamapps/common/Path of Exile/logs/Client.txt");
+        let proton_log = steam.join("steamapps/compatdata/3141592/pfx/drive_c/Program Files (x86)/Grinding Gear Games/Path of Exile/logs/Client.txt");
+        let wine_log = home.join(
+            "Games/path-of-exile/drive_c/Program Files/Grinding Gear Games/Path of Exile 2/logs/Client.txt",
+        );
+        let standalone_log = pf.join("Grinding Gear Games/Path of Exile/logs/Client.txt");
+        touch(&proton_log);
+        touch(&wine_log);
+        touch(&steam_log);
+        touch(&standalone_log);
+        // not a game log
+        touch(&library.join("steamapps/common/Other/logs/Client.txt"));
+
+        let roots = Roots {
+            home: Some(home.clone()),
+            program_files: vec![pf],
+            // same install reachable twice
+            steam: vec![steam.clone(), steam],
+        };
+        let found = discover_in(&roots);
+        assert!(found.iter().all(|c| c.modified.is_some()));
+        let found: Vec<(String, CandidateSource)> =
+            found.into_iter().map(|c| (c.path, c.source)).collect();
+        let s = |p: &PathBuf| p.to_string_lossy().into_owned();
+        assert_eq!(
+            found,
+            vec![
+                (s(&standalone_log), CandidateSource::Standalone),
+                (s(&steam_log), CandidateSource::Steam),
+                (s(&wine_log), CandidateSource::Wine),
+                (s(&proton_log), CandidateSource::Proton),
+            ]
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,932 @@
+use crate::model;
+use chrono::NaiveDateTime;
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+pub trait Len {
+    fn len(&self) -> std::io::Result<u64>;
+}
+
+pub trait FileLineReaderSource: Read + Seek + Len {
+    // switches to another file if the one being read was deleted or rotated
+    fn reopen_if_replaced(&mut self) -> std::io::Result<bool> {
+        Ok(false)
+    }
+}
+
+impl Len for File {
+    fn len(&self) -> std::io::Result<u64> {
+        let m = File::metadata(self)?;
+        Ok(m.len())
+    }
+}
+
+impl FileLineReaderSource for File {}
+
+#[cfg(unix)]
+fn file_id(f: &File) -> std::io::Result<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let m = f.metadata()?;
+    Ok((m.dev(), m.ino()))
+}
+
+// volume serial number and file index, creation time can't be used since file tunneling
+// keeps it for a file recreated under the same name
+#[cfg(windows)]
+fn file_id(f: &File) -> std::io::Result<(u64, u64)> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION,
+    };
+    // SAFETY: all-zero is a valid BY_HANDLE_FILE_INFORMATION, the handle is open while
+    // `f` is borrowed
+    let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+    if unsafe { GetFileInformationByHandle(f.as_raw_handle() as _, &mut info) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let index = ((info.nFileIndexHigh as u64) << 32) | info.nFileIndexLow as u64;
+    Ok((info.dwVolumeSerialNumber as u64, index))
+}
+
+// log file opened by path, notices when the path starts pointing to another file
+pub struct LogFile {
+    file: File,
+    path: PathBuf,
+    id: (u64, u64),
+}
+
+impl LogFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let file = File::open(path.as_ref())?;
+        LogFile::with_file(file, path.as_ref())
+    }
+
+    fn with_file(file: File, path: &Path) -> std::io::Result<Self> {
+        let id = file_id(&file)?;
+        Ok(LogFile {
+            file,
+            path: path.to_path_buf(),
+            id,
+        })
+    }
+}
+
+impl Read for LogFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for LogFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+impl Len for LogFile {
+    fn len(&self) -> std::io::Result<u64> {
+        self.file.len()
+    }
+}
+
+impl FileLineReaderSource for LogFile {
+    fn reopen_if_replaced(&mut self) -> std::io::Result<bool> {
+        let file = match File::open(&self.path) {
+            Ok(f) => f,
+            // deleted and not recreated yet, keep reading old handle
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        if file_id(&file)? == self.id {
+            return Ok(false);
+        }
+        *self = LogFile::with_file(file, &self.path.clone())?;
+        Ok(true)
+    }
+}
+
+// longest unfinished line kept between reads, game lines are much shorter
+const MAX_PENDING: usize = 64 * 1024;
+
+// splits raw bytes into complete lines, unfinished tail (including split multibyte
+// characters) is kept until the rest of it arrives
+#[derive(Default)]
+pub struct LineFramer {
+    pending: Vec<u8>,
+}
+
+impl LineFramer {
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(bytes);
+        let end = match self.pending.iter().rposition(|b| *b == b'\n') {
+            Some(i) => i + 1,
+            None if self.pending.len() > MAX_PENDING => self.pending.len(),
+            None => return vec![],
+        };
+        let rest = self.pending.split_off(end);
+        let complete = std::mem::replace(&mut self.pending, rest);
+
+        complete
+            .strip_suffix(b"\n")
+            .unwrap_or(&complete)
+            .split(|b| *b == b'\n')
+            .map(|l| {
+                let l = l.strip_suffix(b"\r").unwrap_or(l);
+                String::from_utf8_lossy(l).into_owned()
+            })
+            .collect()
+    }
+
+    // unfinished line left when input is over
+    pub fn finish(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let line = std::mem::take(&mut self.pending);
+        let line = line.strip_suffix(b"\r").unwrap_or(&line);
+        Some(String::from_utf8_lossy(line).into_owned())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct BackfillSettings {
+    // lines older than this by game timestamp aren't replayed, 0 disables backfill
+    #[serde(default = "default_backfill_minutes")]
+    pub minutes: u64,
+    // how far back from the end of the log to look at most
+    #[serde(default = "default_backfill_megabytes")]
+    pub megabytes: u64,
+}
+
+fn default_backfill_minutes() -> u64 {
+    15
+}
+
+fn default_backfill_megabytes() -> u64 {
+    2
+}
+
+impl Default for BackfillSettings {
+    fn default() -> Self {
+        BackfillSettings {
+            minutes: default_backfill_minutes(),
+            megabytes: default_backfill_megabytes(),
+        }
+    }
+}
+
+const BACKFILL_CHUNK: u64 = 64 * 1024;
+
+// time of the first whole line of data, data may start in the middle of a line
+fn first_line_time(data: &[u8], whole: bool) -> Option<NaiveDateTime> {
+    let start = if whole {
+        0
+    } else {
+        data.iter().position(|b| *b == b'\n')? + 1
+    };
+    let head = &data[start..data.len().min(start + 19)];
+    model::line_time(std::str::from_utf8(head).ok()?)
+}
+
+// receiver of complete lines read from the log
+pub trait LineSink {
+    fn add_lines(&mut self, lines: Vec<String>);
+}
+
+// model locked for the whole batch, for tools reading the log on a single thread
+impl LineSink for Arc<Mutex<model::Model>> {
+    fn add_lines(&mut self, lines: Vec<String>) {
+        // a panicking subscriber must not stop reading for good
+        let mut lock = self.lock().unwrap_or_else(PoisonError::into_inner);
+        for l in lines.iter() {
+            let r = lock.try_add(l);
+            debug!("result processing line: {} {:?}", l, r);
+        }
+    }
+}
+
+// where a reader stopped, for another reader to go on with the same file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    id: (u64, u64),
+    offset: u64,
+}
+
+pub struct FileLineReader<F> {
+    byte_count: u64,
+    sock: F,
+    model: Box<dyn LineSink + Send>,
+    framer: LineFramer,
+}
+
+impl<F> FileLineReader<F> {
+    pub fn with_file<S: LineSink + Send + 'static>(
+        model: S,
+        fp: &str,
+    ) -> Result<FileLineReader<LogFile>, anyhow::Error> {
+        FileLineReader::new(model, LogFile::open(fp)?)
+    }
+}
+
+impl FileLineReader<LogFile> {
+    // reader at the end of log, lines written shortly before `now` are replayed first
+    pub fn open_with_backfill<S: LineSink + Send + 'static>(
+        model: S,
+        fp: &str,
+        backfill: &BackfillSettings,
+        now: NaiveDateTime,
+    ) -> Result<Self, anyhow::Error> {
+        let mut flr = FileLineReader::<LogFile>::with_file(model, fp)?;
+        match flr.backfill(backfill, now) {
+            Ok(n) => debug!("backfilled {} lines from {}", n, fp),
+            Err(e) => error!("can't backfill from {}: {}", fp, e),
+        }
+        Ok(flr)
+    }
+
+    // unfinished last line is read again by the resumed reader
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            id: self.sock.id,
+            offset: self
+                .byte_count
+                .saturating_sub(self.framer.pending.len() as u64),
+        }
+    }
+
+    // reader going on from `checkpoint`, None when `fp` is another file by now
+    // or got shorter than what was read
+    pub fn resume<S: LineSink + Send + 'static>(
+        model: S,
+        fp: &str,
+        checkpoint: &Checkpoint,
+    ) -> Result<Option<Self>, anyhow::Error> {
+        let mut file = LogFile::open(fp)?;
+        if file.id != checkpoint.id || file.len()? < checkpoint.offset {
+            return Ok(None);
+        }
+        file.seek(SeekFrom::Start(checkpoint.offset))?;
+        Ok(Some(FileLineReader {
+            byte_count: checkpoint.offset,
+            sock: file,
+            model: Box::new(model),
+            framer: LineFramer::default(),
+        }))
+    }
+}
+
+impl<F: FileLineReaderSource> FileLineReader<F> {
+    pub fn new<S: LineSink + Send + 'static>(model: S, mut fp: F) -> Result<Self, anyhow::Error> {
+        fp.seek(SeekFrom::End(0))?;
+        let size = fp.len()?;
+
+        Ok(FileLineReader {
+            byte_count: size,
+            sock: fp,
+            model: Box::new(model),
+            framer: LineFramer::default(),
+        })
+    }
+
+    // replays lines written shortly before the reader was created, scanning back from the end
+    // until lines get older than `now` minus configured minutes
+    pub fn backfill(
+        &mut self,
+        settings: &BackfillSettings,
+        now: NaiveDateTime,
+    ) -> Result<usize, anyhow::Error> {
+        let limit = settings.megabytes * 1024 * 1024;
+        if settings.minutes == 0 || limit == 0 {
+            return Ok(0);
+        }
+        let cutoff = now - chrono::Duration::minutes(settings.minutes as i64);
+        let end = self.byte_count;
+        let mut start = end;
+        let mut data = vec![];
+        while start > 0 && end - start < limit {
+            let size = BACKFILL_CHUNK.min(start).min(limit - (end - start));
+            start -= size;
+            self.sock.seek(SeekFrom::Start(start))?;
+            let mut chunk = vec![0; size as usize];
+            self.sock.read_exact(&mut chunk)?;
+            chunk.extend_from_slice(&data);
+            data = chunk;
+            if matches!(first_line_time(&data, start == 0), Some(t) if t < cutoff) {
+                break;
+            }
+        }
+        self.sock.seek(SeekFrom::Start(end))?;
+
+        let skip = if start == 0 {
+            0
+        } else {
+            data.iter()
+                .position(|b| *b == b'\n')
+                .map_or(data.len(), |i| i + 1)
+        };
+        // unfinished last line is completed by live reading
+        let mut framer = LineFramer::default();
+        let lines = framer.push(&data[skip..]);
+        self.framer = framer;
+
+        let mut in_window = false;
+        let recent: Vec<String> = lines
+            .into_iter()
+            .filter(|l| {
+                // lines without timestamp belong to the previous line
+                if let Some(t) = model::line_time(l) {
+                    in_window = t >= cutoff;
+                }
+                in_window
+            })
+            .collect();
+        let count = recent.len();
+        self.model.add_lines(recent);
+        debug!(
+            "backfill replayed {} lines from {} bytes",
+            count,
+            end - start
+        );
+        Ok(count)
+    }
+
+    fn restart(&mut self) -> Result<(), anyhow::Error> {
+        self.sock.seek(SeekFrom::Start(0))?;
+        self.byte_count = 0;
+        self.framer = LineFramer::default();
+        Ok(())
+    }
+
+    // number of complete lines handed to the model
+    pub fn process_new_content(&mut self) -> Result<usize, anyhow::Error> {
+        if self.sock.reopen_if_replaced()? {
+            // recreated or rotated file, everything in it is new
+            debug!("source replaced, reading from start");
+            self.restart()?;
+        }
+
+        let new_size = self.sock.len()?;
+        if new_size < self.byte_count {
+            // truncated in place, game started writing from the beginning
+            debug!("source truncated, reading from start");
+            self.restart()?;
+        }
+        if new_size == self.byte_count {
+            debug!("no new content in source");
+            return Ok(0);
+        }
+
+        // may read past `new_size` when the log grew meanwhile, all of it is counted
+        // so a later truncation below the read position is noticed
+        let mut contents = vec![];
+        self.sock.read_to_end(&mut contents)?;
+        self.byte_count += contents.len() as u64;
+        debug!("read {} data to process", contents.len());
+        let lines = self.framer.push(&contents);
+        let count = lines.len();
+        self.model.add_lines(lines);
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utilities::Callable;
+    use proptest::prelude::*;
+    use std::io::Cursor;
+    use std::io::Write;
+
+    #[derive(Clone)]
+    struct CursorMut {
+        m: Arc<Mutex<Cursor<Vec<u8>>>>,
+    }
+
+    impl CursorMut {
+        fn new() -> Self {
+            CursorMut {
+                m: Arc::new(Mutex::new(Cursor::new(vec![]))),
+            }
+        }
+    }
+
+    impl Read for CursorMut {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.m.lock().unwrap().read(buf)
+        }
+    }
+
+    impl Seek for CursorMut {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.m.lock().unwrap().seek(pos)
+        }
+    }
+
+    impl std::io::Write for CursorMut {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.m.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Len for CursorMut {
+        fn len(&self) -> std::io::Result<u64> {
+            let mut m = self.m.lock().unwrap();
+            let cp = m.position();
+            m.seek(SeekFrom::End(0))?;
+            let size = m.position() - cp;
+            m.seek(SeekFrom::Start(cp))?;
+            Ok(size)
+        }
+    }
+
+    impl FileLineReaderSource for CursorMut {}
+
+    struct Pos {
+        read: u64,
+        write: u64,
+    }
+
+    #[derive(Clone)]
+    struct DoubleBuffer {
+        b: CursorMut,
+        pos: Arc<Mutex<Pos>>,
+    }
+
+    impl DoubleBuffer {
+        fn new() -> Self {
+            DoubleBuffer {
+                b: CursorMut::new(),
+                pos: Arc::new(Mutex::new(Pos { read: 0, write: 0 })),
+            }
+        }
+    }
+
+    impl DoubleBuffer {
+        fn truncate(&self) {
+            let mut m = self.pos.lock().unwrap();
+            self.b.m.lock().unwrap().get_mut().clear();
+            m.write = 0;
+        }
+    }
+
+    impl Read for DoubleBuffer {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let mut m = self.pos.lock().unwrap();
+            self.b.seek(SeekFrom::Start(m.read))?;
+            let s = self.b.read(buf)?;
+            m.read += s as u64;
+            Ok(s)
+        }
+    }
+
+    impl Write for DoubleBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let mut m = self.pos.lock().unwrap();
+            self.b.seek(SeekFrom::Start(m.write))?;
+            let s = self.b.write(buf)?;
+            m.write += s as u64;
+            Ok(s)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for DoubleBuffer {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            let s = self.b.seek(pos)?;
+            self.pos.lock().unwrap().read = s;
+            Ok(s)
+        }
+    }
+
+    impl Len for DoubleBuffer {
+        fn len(&self) -> std::io::Result<u64> {
+            Ok(self.pos.lock().unwrap().write)
+        }
+    }
+
+    impl FileLineReaderSource for DoubleBuffer {}
+
+    // log appended to right after the reader took its size
+    #[derive(Clone)]
+    struct Racing {
+        b: DoubleBuffer,
+        late: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Read for Racing {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.b.read(buf)
+        }
+    }
+
+    impl Seek for Racing {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.b.seek(pos)
+        }
+    }
+
+    impl Len for Racing {
+        fn len(&self) -> std::io::Result<u64> {
+            let size = self.b.len()?;
+            let late = std::mem::take(&mut *self.late.lock().unwrap());
+            self.b.clone().write_all(&late)?;
+            Ok(size)
+        }
+    }
+
+    impl FileLineReaderSource for Racing {}
+
+    #[test]
+    fn double_buffer_usage() {
+        let mut db = DoubleBuffer::new();
+        db.write("buf".as_bytes()).unwrap();
+        let mut b = [0; 3];
+        db.read(&mut b).unwrap();
+        assert_eq!(b, "buf".as_bytes());
+    }
+
+    const INCOMING_MSG: &'static [u8] = r#"2023/10/13 01:54:50 1054470421 cffb0719 [INFO Client 30680] @From SambaLe: Hi, I would like to buy your The Pandemonius, Jade Amulet listed for 4 divine in Ancestor (stash tab "pub"; position: left 11, top 1)"#.as_bytes();
+
+    #[test]
+    fn basic_usage() {
+        let mut buf = DoubleBuffer::new();
+        let model = Arc::new(Mutex::new(model::Model::new()));
+        let mut rdr = FileLineReader::new(Arc::clone(&model), buf.clone()).unwrap();
+        let clb = Callable::new();
+
+        {
+            let clb = clb.clone();
+            model.lock().unwrap().incoming_subscribe(move |_ig| {
+                clb.call();
+            });
+        }
+        buf.write(INCOMING_MSG).unwrap();
+        buf.write(b"\n").unwrap();
+        buf.write(INCOMING_MSG).unwrap();
+        rdr.process_new_content().unwrap();
+
+        // second line is still being written
+        assert_eq!(clb.count(), 1);
+
+        buf.write(b"\n").unwrap();
+        buf.write(INCOMING_MSG).unwrap();
+        rdr.process_new_content().unwrap();
+
+        assert_eq!(clb.count(), 2);
+
+        buf.write_all(b"\r\n").unwrap();
+        rdr.process_new_content().unwrap();
+
+        assert_eq!(clb.count(), 3);
+    }
+
+    #[test]
+    fn split_multibyte_and_invalid_utf8() {
+        let mut framer = LineFramer::default();
+        let line = "@From 匚丹匚丹几丹: hi\n".as_bytes();
+        // cut in the middle of the first character of the name
+        assert!(framer.push(&line[..7]).is_empty());
+        assert_eq!(framer.push(&line[7..]), vec!["@From 匚丹匚丹几丹: hi"]);
+
+        assert_eq!(
+            framer.push(b"bad \xff\xfe bytes\nnext"),
+            vec!["bad \u{fffd}\u{fffd} bytes"]
+        );
+        assert_eq!(framer.push(b"\n"), vec!["next"]);
+        assert_eq!(framer.finish(), None);
+        assert!(framer.push(b"last\r").is_empty());
+        assert_eq!(framer.finish(), Some("last".to_string()));
+    }
+
+    #[test]
+    fn truncated_source() {
+        let mut buf = DoubleBuffer::new();
+        let model = Arc::new(Mutex::new(model::Model::new()));
+        let mut rdr = FileLineReader::new(Arc::clone(&model), buf.clone()).unwrap();
+        let clb = Callable::new();
+        {
+            let clb = clb.clone();
+            model
+                .lock()
+                .unwrap()
+                .incoming_subscribe(move |_ig| clb.call());
+        }
+
+        buf.write_all(b"some noise before the trade\n").unwrap();
+        buf.write_all(INCOMING_MSG).unwrap();
+        buf.write_all(b"\nunfinished").unwrap();
+        rdr.process_new_content().unwrap();
+        assert_eq!(clb.count(), 1);
+
+        // new content is shorter than what was read, unfinished tail is dropped
+        buf.truncate();
+        buf.write_all(INCOMING_MSG).unwrap();
+        buf.write_all(b"\n").unwrap();
+        rdr.process_new_content().unwrap();
+        assert_eq!(clb.count(), 2);
+
+        buf.truncate();
+        rdr.process_new_content().unwrap();
+        assert_eq!(clb.count(), 2);
+    }
+
+    #[test]
+    fn truncated_after_growing_while_read() {
+        let mut buf = DoubleBuffer::new();
+        let late = Arc::new(Mutex::new(vec![]));
+        let racing = Racing {
+            b: buf.clone(),
+            late: Arc::clone(&late),
+        };
+        let model = Arc::new(Mutex::new(model::Model::new()));
+        let mut rdr = FileLineReader::new(Arc::clone(&model), racing).unwrap();
+        let clb = Callable::new();
+        {
+            let clb = clb.clone();
+            model
+                .lock()
+                .unwrap()
+                .incoming_subscribe(move |_ig| clb.call());
+        }
+
+        buf.write_all(INCOMING_MSG).unwrap();
+        buf.write_all(b"\n").unwrap();
+        *late.lock().unwrap() = b"written while the size was taken\n".to_vec();
+        rdr.process_new_content().unwrap();
+        assert_eq!(clb.count(), 1);
+
+        // shorter than what was read, longer than the size seen before reading
+        buf.truncate();
+        buf.write_all(b"noise\n").unwrap();
+        buf.write_all(INCOMING_MSG).unwrap();
+        buf.write_all(b"\n").unwrap();
+        rdr.process_new_content().unwrap();
+        assert_eq!(clb.count(), 2);
+    }
+
+    fn timed_line(time: &str, body: &str) -> String {
+        format!(
+            "2023/10/13 {} 1054470421 cffb0719 [INFO Client 30680] {}\n",
+            time, body
+        )
+    }
+
+    fn trade_from(player: &str) -> String {
+        format!(
+            r#"@From {}: Hi, I would like to buy your Tabula Rasa Simple Robe listed for 8 chaos in Ancestor (stash tab "~price 8 chaos"; position: left 1, top 1)"#,
+            player
+        )
+    }
+
+    #[test]
+    fn backfill_recent_lines() {
+        let mut buf = DoubleBuffer::new();
+        buf.write_all(b"tail of older line\n").unwrap();
+        buf.write_all(timed_line("01:00:00", &trade_from("old")).as_bytes())
+            .unwrap();
+        // a bit more than a megabyte
+        for _ in 0..15000 {
+            buf.write_all(timed_line("01:30:00", "noise noise noise noise").as_bytes())
+                .unwrap();
+        }
+        buf.write_all(timed_line("01:50:00", &trade_from("recent")).as_bytes())
+            .unwrap();
+        buf.write_all(timed_line("01:55:00", &trade_from("latest")).as_bytes())
+            .unwrap();
+        buf.write_all(&timed_line("01:55:30", &trade_from("partial")).as_bytes()[..80])
+            .unwrap();
+
+        let now =
+            NaiveDateTime::parse_from_str("2023/10/13 02:00:00", "%Y/%m/%d %H:%M:%S").unwrap();
+        let seen = |settings: BackfillSettings| -> Vec<String> {
+            let model = Arc::new(Mutex::new(model::Model::new()));
+            let mut rdr = FileLineReader::new(Arc::clone(&model), buf.clone()).unwrap();
+            rdr.backfill(&settings, now).unwrap();
+            let trades = model.lock().unwrap();
+            trades
+                .ordered_trades(&model::TradeType::Incoming)
+                .iter()
+                .map(|t| t.player_name().to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            seen(BackfillSettings {
+                minutes: 15,
+                megabytes: 2
+            }),
+            vec!["recent", "latest"]
+        );
+        assert_eq!(
+            seen(BackfillSettings {
+                minutes: 120,
+                megabytes: 2
+            }),
+            vec!["old", "recent", "latest"]
+        );
+        assert_eq!(
+            seen(BackfillSettings {
+                minutes: 120,
+                megabytes: 1
+            }),
+            vec!["recent", "latest"]
+        );
+        assert_eq!(
+            seen(BackfillSettings {
+                minutes: 0,
+                megabytes: 2
+            }),
+            Vec::<String>::new()
+        );
+
+        // partial line is finished by live reading
+        let model = Arc::new(Mutex::new(model::Model::new()));
+        let mut rdr = FileLineReader::new(Arc::clone(&model), buf.clone()).unwrap();
+        rdr.backfill(&BackfillSettings::default(), now).unwrap();
+        buf.write_all(&timed_line("01:55:30", &trade_from("partial")).as_bytes()[80..])
+            .unwrap();
+        rdr.process_new_content().unwrap();
+        assert_eq!(
+            model
+                .lock()
+                .unwrap()
+                .ordered_trades(&model::TradeType::Incoming)
+                .len(),
+            3
+        );
+    }
+
+    struct TempLog {
+        dir: PathBuf,
+    }
+
+    impl TempLog {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("flr-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            File::create(dir.join("Client.txt")).unwrap();
+            TempLog { dir }
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.dir.join(name)
+        }
+
+        fn append(&self, name: &str, data: &[u8]) {
+            let mut f = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path(name))
+                .unwrap();
+            f.write_all(data).unwrap();
+        }
+    }
+
+    impl Drop for TempLog {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn file_reader(log: &TempLog) -> (FileLineReader<LogFile>, Callable) {
+        let model = Arc::new(Mutex::new(model::Model::new()));
+        let path = log.path("Client.txt");
+        let rdr = FileLineReader::<LogFile>::with_file(Arc::clone(&model), path.to_str().unwrap())
+            .unwrap();
+        let clb = Callable::new();
+        {
+            let clb = clb.clone();
+            model
+                .lock()
+                .unwrap()
+                .incoming_subscribe(move |_ig| clb.call());
+        }
+        (rdr, clb)
+    }
+
+    // deleting or renaming an open file is not allowed on windows
+    #[cfg(unix)]
+    #[test]
+    fn deleted_and_recreated_file() {
+        let log = TempLog::new();
+        let (mut rdr, clb) = file_reader(&log);
+
+        log.append("Client.txt", INCOMING_MSG);
+        log.append("Client.txt", b"\n");
+        rdr.process_new_content().unwrap();
+        assert_eq!(clb.count(), 1);
+
+        std::fs::remove_file(log.path("Client.txt")).unwrap();
+        rdr.process_new_content().unwrap();
+        assert_eq!(clb.count(), 1);
+
+        log.append("Client.txt", INCOMING_MSG);
+        log.append("Client.txt", b"\n");
+        rdr.process_new_content().unwrap();
+        assert_eq!(clb.count(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rotated_file() {
+        let log = TempLog::new();
+        let (mut rdr, clb) = file_reader(&log);
+
+        log.append("Client.txt", b"some noise\n");
+        rdr.process_new_content().unwrap();
+
+        std::fs::rename(log.path("Client.txt"), log.path("Client.1.txt")).unwrap();
+        // lines written to rotated file are not read anymore
+        log.append("Client.1.txt", INCOMING_MSG);
+        log.append("Client.1.txt", b"\n");
+        log.append("Client.txt", INCOMING_MSG);
+        log.append("Client.txt", b"\n");
+        rdr.process_new_content().unwrap();
+        assert_eq!(clb.count(), 1);
+
+        log.append("Client.1.txt", INCOMING_MSG);
+        log.append("Client.1.txt", b"\n");
+        rdr.process_new_content().unwrap();
+        assert_eq!(clb.count(), 1);
+    }
+
+    fn feed_in_chunks(data: &[u8], cuts: Vec<usize>, mut f: impl FnMut(&[u8])) {
+        let mut cuts: Vec<usize> = cuts.into_iter().map(|c| c.min(data.len())).collect();
+        cuts.sort_unstable();
+        cuts.push(data.len());
+        let mut prev = 0;
+        for c in cuts {
+            f(&data[prev..c]);
+            prev = c;
+        }
+    }
+
+    fn noise_line() -> impl Strategy<Value = Vec<u8>> {
+        proptest::collection::vec(
+            any::<u8>().prop_filter("no separators", |b| *b != b'\n' && *b != b'@'),
+            0..40,
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn framer_chunking_independent(
+            data in proptest::collection::vec(any::<u8>(), 0..512),
+            cuts in proptest::collection::vec(0usize..512, 0..16),
+        ) {
+            let expected = LineFramer::default().push(&data);
+            let mut framer = LineFramer::default();
+            let mut lines = vec![];
+            feed_in_chunks(&data, cuts, |chunk| lines.extend(framer.push(chunk)));
+            prop_assert_eq!(lines, expected);
+        }
+
+        #[test]
+        fn reader_sees_every_trade(
+            names in proptest::collection::vec("[a-zA-Z0-9_匚丹几äöü]{1,8}", 1..8),
+            noise in proptest::collection::vec(noise_line(), 8),
+            cuts in proptest::collection::vec(0usize..4096, 0..32),
+        ) {
+            let mut data = vec![];
+            let mut expected = vec![];
+            for (i, (name, noise)) in names.iter().zip(noise.iter()).enumerate() {
+                let player = format!("p{}{}", i, name);
+                data.extend_from_slice(noise);
+                data.push(b'\n');
+                data.extend_from_slice(format!(r#"2023/10/13 01:54:50 1054470421 cffb0719 [INFO Client 30680] @From {}: Hi, I would like to buy your Tabula Rasa Simple Robe listed for 8 chaos in Ancestor (stash tab "~price 8 chaos"; position: left 1, top 1)"#, player).as_bytes());
+                data.push(b'\n');
+                expected.push(player);
+            }
+
+            let mut buf = DoubleBuffer::new();
+            let model = Arc::new(Mutex::new(model::Model::new()));
+            let mut rdr = FileLineReader::new(Arc::clone(&model), buf.clone()).unwrap();
+            let seen = Arc::new(Mutex::new(vec![]));
+            {
+                let seen = Arc::clone(&seen);
+                model.lock().unwrap().incoming_subscribe(move |ig| {
+                    seen.lock().unwrap().push(ig.player_name().to_string());
+                });
+            }
+
+            feed_in_chunks(&data, cuts, |chunk| {
+                buf.write_all(chunk).unwrap();
+                rdr.process_new_content().unwrap();
+            });
+            prop_assert_eq!(seen.lock().unwrap().clone(), expected);
+        }
+    }
+}
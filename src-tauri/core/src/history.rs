@@ -0,0 +1,110 @@
+use crate::settings::write_atomic;
+use log::error;
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Sender},
+    thread::JoinHandle,
+};
+
+// enough to cover any backfill window
+const MAX_HANDLED: usize = 1000;
+
+// writes the keys on a thread of its own so closing trades doesn't wait for the disk,
+// only the latest keys are written when trades are closed faster than that
+struct Saver {
+    tx: Sender<Vec<String>>,
+    thread: JoinHandle<()>,
+}
+
+impl Saver {
+    fn spawn(path: PathBuf) -> Self {
+        let (tx, rx) = channel::<Vec<String>>();
+        let thread = std::thread::spawn(move || {
+            while let Ok(mut keys) = rx.recv() {
+                while let Ok(newer) = rx.try_recv() {
+                    keys = newer;
+                }
+                let r = serde_json::to_vec(&keys)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|b| write_atomic(&path, &b));
+                if let Err(e) = r {
+                    error!("can't save handled trades: {}", e);
+                }
+            }
+        });
+        Saver { tx, thread }
+    }
+}
+
+// log keys of trades closed by user, kept between runs so backfill doesn't bring them back
+#[derive(Default)]
+pub struct HandledTrades {
+    saver: Option<Saver>,
+    keys: VecDeque<String>,
+}
+
+impl HandledTrades {
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let keys = std::fs::read(path.as_ref())
+            .ok()
+            .and_then(|b| serde_json::from_slice(&b).ok())
+            .unwrap_or_default();
+        HandledTrades {
+            saver: Some(Saver::spawn(path.as_ref().to_path_buf())),
+            keys,
+        }
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.keys.iter().any(|k| k == key)
+    }
+
+    pub fn insert(&mut self, key: String) {
+        if self.contains(&key) {
+            return;
+        }
+        self.keys.push_back(key);
+        while self.keys.len() > MAX_HANDLED {
+            self.keys.pop_front();
+        }
+        if let Some(saver) = self.saver.as_ref() {
+            let _ = saver.tx.send(self.keys.iter().cloned().collect());
+        }
+    }
+}
+
+// pending save is finished before the keys go away
+impl Drop for HandledTrades {
+    fn drop(&mut self) {
+        if let Some(Saver { tx, thread }) = self.saver.take() {
+            drop(tx);
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persisted_between_loads() {
+        let path = std::env::temp_dir().join(format!("handled-{}.json", uuid::Uuid::new_v4()));
+        let mut handled = HandledTrades::load(&path);
+        assert!(!handled.contains("a"));
+        for i in 0..MAX_HANDLED + 1 {
+            handled.insert(i.to_string());
+        }
+        handled.insert("1".to_string());
+        // dropping waits for the pending save
+        drop(handled);
+        assert!(!path.with_extension("json.tmp").exists());
+
+        let handled = HandledTrades::load(&path);
+        assert!(!handled.contains("0"));
+        assert!(handled.contains("1"));
+        assert!(handled.contains(&MAX_HANDLED.to_string()));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::commands::TradeAction;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// accelerators in tauri format, e.g. "Alt+1" or "CmdOrCtrl+Shift+I"
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct Hotkeys {
+    #[serde(default)]
+    pub invite: Option<String>,
+    #[serde(default)]
+    pub trade: Option<String>,
+    #[serde(default)]
+    pub kick: Option<String>,
+    #[serde(default)]
+    pub thank: Option<String>,
+    #[serde(default)]
+    pub close: Option<String>,
+    #[serde(default)]
+    pub cycle: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HotkeyAction {
+    Trade(TradeAction),
+    Close,
+    Cycle,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct HotkeyConflict {
+    pub accelerator: String,
+    pub reason: String,
+}
+
+impl Hotkeys {
+    pub fn bindings(&self) -> Vec<(&str, HotkeyAction)> {
+        [
+            (&self.invite, HotkeyAction::Trade(TradeAction::Invite)),
+            (&self.trade, HotkeyAction::Trade(TradeAction::Trade)),
+            (&self.kick, HotkeyAction::Trade(TradeAction::Kick)),
+            (&self.thank, HotkeyAction::Trade(TradeAction::Thank)),
+            (&self.close, HotkeyAction::Close),
+            (&self.cycle, HotkeyAction::Cycle),
+        ]
+        .into_iter()
+        .filter_map(|(acc, action)| acc.as_deref().map(|a| (a, action)))
+        .filter(|(acc, _)| !acc.trim().is_empty())
+        .collect()
+    }
+
+    // accelerators bound to more than one action, these can't be registered at all
+    pub fn duplicates(&self) -> Vec<HotkeyConflict> {
+        let bindings = self.bindings();
+        let mut conflicts: Vec<HotkeyConflict> = vec![];
+        for (i, (acc, _)) in bindings.iter().enumerate() {
+            let dup = bindings[..i]
+                .iter()
+                .any(|(other, _)| other.eq_ignore_ascii_case(acc));
+            if dup
+                && !conflicts
+                    .iter()
+                    .any(|c| c.accelerator.eq_ignore_ascii_case(acc))
+            {
+                conflicts.push(HotkeyConflict {
+                    accelerator: acc.to_string(),
+                    reason: "bound to several actions".to_string(),
+                });
+            }
+        }
+        conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_and_duplicates() {
+        let hk = Hotkeys {
+            invite: Some("Alt+1".to_string()),
+            trade: Some("alt+1".to_string()),
+            kick: Some(" ".to_string()),
+            close: Some("Alt+3".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            hk.bindings(),
+            vec![
+                ("Alt+1", HotkeyAction::Trade(TradeAction::Invite)),
+                ("alt+1", HotkeyAction::Trade(TradeAction::Trade)),
+                ("Alt+3", HotkeyAction::Close),
+            ]
+        );
+        assert_eq!(
+            hk.duplicates(),
+            vec![HotkeyConflict {
+                accelerator: "alt+1".to_string(),
+                reason: "bound to several actions".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,406 @@
+use crate::file_line_reader::{BackfillSettings, FileLineReader, LogFile};
+use crate::log_watcher::{touches_log, LogEvent};
+use crate::model_actor::ModelHandle;
+use log::{debug, error};
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{mpsc::Receiver, Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, SystemTime},
+};
+use ts_rs::TS;
+
+// latency samples kept for percentiles
+const MAX_SAMPLES: usize = 256;
+// pause before reading again after a failed read, so a broken log doesn't spin the reader
+const RESTART_DELAY: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestStats {
+    // batches of changes that had new lines
+    #[ts(type = "number")]
+    pub batches: u64,
+    #[ts(type = "number")]
+    pub lines: u64,
+    #[ts(type = "number")]
+    pub restarts: u64,
+    // from the log being written to its lines being handled by the model
+    pub last_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+struct Metrics {
+    batches: u64,
+    lines: u64,
+    restarts: u64,
+    samples: VecDeque<Duration>,
+}
+
+impl Metrics {
+    fn record(&mut self, lines: usize, latency: Option<Duration>) {
+        self.batches += 1;
+        self.lines += lines as u64;
+        if let Some(l) = latency {
+            if self.samples.len() == MAX_SAMPLES {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(l);
+        }
+    }
+
+    fn stats(&self) -> IngestStats {
+        let mut sorted: Vec<Duration> = self.samples.iter().cloned().collect();
+        sorted.sort();
+        let ms = |d: &Duration| d.as_secs_f64() * 1000.0;
+        let percentile = |p: usize| {
+            sorted
+                .get((sorted.len() * p / 100).min(sorted.len().saturating_sub(1)))
+                .map(ms)
+        };
+        IngestStats {
+            batches: self.batches,
+            lines: self.lines,
+            restarts: self.restarts,
+            last_ms: self.samples.back().map(ms),
+            p50_ms: percentile(50),
+            p95_ms: percentile(95),
+            max_ms: sorted.last().map(ms),
+        }
+    }
+}
+
+// lines read from one change of the log
+struct Batch {
+    lines: usize,
+    written: Option<SystemTime>,
+}
+
+// reads the log into the model, reader is None until the log exists
+pub struct LogIngest {
+    model: ModelHandle,
+    logpath: String,
+    backfill: BackfillSettings,
+    reader: Option<FileLineReader<LogFile>>,
+    metrics: Metrics,
+}
+
+impl LogIngest {
+    pub fn new(model: ModelHandle, logpath: &str, backfill: &BackfillSettings) -> Self {
+        let mut ingest = LogIngest {
+            model,
+            logpath: logpath.to_string(),
+            backfill: backfill.clone(),
+            reader: None,
+            metrics: Metrics::default(),
+        };
+        ingest.open();
+        ingest
+    }
+
+    // switches to another log, recent lines of it are backfilled
+    pub fn set_log(&mut self, logpath: &str, backfill: &BackfillSettings) {
+        self.logpath = logpath.to_string();
+        self.backfill = backfill.clone();
+        self.open();
+    }
+
+    pub fn stats(&self) -> IngestStats {
+        self.metrics.stats()
+    }
+
+    fn open(&mut self) {
+        let now = chrono::Local::now().naive_local();
+        self.reader = FileLineReader::open_with_backfill(
+            self.model.clone(),
+            &self.logpath,
+            &self.backfill,
+            now,
+        )
+        .ok();
+        debug!(
+            "file_line_reader of {} initialized: {}",
+            self.logpath,
+            self.reader.is_some()
+        );
+    }
+
+    // reopened after a failed read, the same file is read on from where the reader stopped
+    // so lines written meanwhile aren't lost, a replaced one is backfilled
+    fn restart(&mut self) {
+        if let Some(checkpoint) = self.reader.take().map(|r| r.checkpoint()) {
+            match FileLineReader::resume(self.model.clone(), &self.logpath, &checkpoint) {
+                Ok(Some(r)) => {
+                    debug!("resuming {} at {:?}", self.logpath, checkpoint);
+                    self.reader = Some(r);
+                    return;
+                }
+                Ok(None) => debug!("{} was replaced, backfilling it", self.logpath),
+                Err(e) => debug!("can't resume {}: {}", self.logpath, e),
+            }
+        }
+        self.open();
+    }
+
+    // batch is None when no new lines were read
+    fn handle(&mut self, paths: &[PathBuf]) -> anyhow::Result<Option<Batch>> {
+        if !touches_log(paths, &self.logpath) {
+            return Ok(None);
+        }
+        let reader = match self.reader.as_mut() {
+            Some(r) => r,
+            None => {
+                // log did not exist before, start reading it once created
+                self.open();
+                return Ok(None);
+            }
+        };
+        let written = std::fs::metadata(&self.logpath)
+            .and_then(|m| m.modified())
+            .ok();
+        let lines = reader.process_new_content()?;
+        Ok((lines > 0).then_some(Batch { lines, written }))
+    }
+}
+
+// lines are only queued to the model, it's waited for without the lock held so
+// stats and log switches of the app aren't held up by a busy model
+fn record(ingest: &Mutex<LogIngest>, model: &ModelHandle, batch: Batch) {
+    model.call(|_| ());
+    let latency = batch
+        .written
+        .and_then(|w| SystemTime::now().duration_since(w).ok());
+    debug!("handled {} lines in {:?} since write", batch.lines, latency);
+    lock(ingest).metrics.record(batch.lines, latency);
+}
+
+fn lock(ingest: &Mutex<LogIngest>) -> MutexGuard<'_, LogIngest> {
+    // state is rebuilt after a panic, so poisoning is of no concern
+    ingest.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// dedicated reader thread, changes are read as soon as they arrive
+pub struct IngestHandle {
+    ingest: Arc<Mutex<LogIngest>>,
+}
+
+impl IngestHandle {
+    // failures of the watcher itself are passed to `on_watch_error`
+    pub fn spawn<E>(ingest: LogIngest, rx: Receiver<LogEvent>, on_watch_error: E) -> Self
+    where
+        E: Fn(String) + Send + 'static,
+    {
+        let ingest = Arc::new(Mutex::new(ingest));
+        let i = Arc::clone(&ingest);
+        std::thread::Builder::new()
+            .name("log-reader".to_string())
+            .spawn(move || run(i, rx, on_watch_error))
+            .expect("can't start log reader");
+        IngestHandle { ingest }
+    }
+
+    pub fn with<R, F: FnOnce(&mut LogIngest) -> R>(&self, f: F) -> R {
+        f(&mut lock(&self.ingest))
+    }
+}
+
+fn run<E: Fn(String)>(ingest: Arc<Mutex<LogIngest>>, rx: Receiver<LogEvent>, on_watch_error: E) {
+    let model = lock(&ingest).model.clone();
+    while let Ok(first) = rx.recv() {
+        // burst of writes queued meanwhile is read at once
+        let mut paths = vec![];
+        for ev in std::iter::once(first).chain(rx.try_iter()) {
+            match ev {
+                Ok(p) => paths.extend(p),
+                Err(e) => on_watch_error(e.to_string()),
+            }
+        }
+        if paths.is_empty() {
+            continue;
+        }
+
+        let r = catch_unwind(AssertUnwindSafe(|| -> anyhow::Result<()> {
+            let batch = lock(&ingest).handle(&paths)?;
+            if let Some(b) = batch {
+                record(&ingest, &model, b);
+            }
+            Ok(())
+        }));
+        let error = match r {
+            Ok(Ok(())) => continue,
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => "reader panicked".to_string(),
+        };
+        error!("log reader failed, restarting: {}", error);
+        std::thread::sleep(RESTART_DELAY);
+        let mut i = lock(&ingest);
+        i.metrics.restarts += 1;
+        i.restart();
+    }
+    debug!("log reader stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_watcher::watch_log;
+    use crate::model::Model;
+    use crate::model_actor::ModelEvent;
+    use std::{
+        io::Write,
+        sync::mpsc::{channel, Sender},
+        time::Instant,
+    };
+
+    const TRADE: &str = "2023/10/13 01:54:50 1054470421 cffb0719 [INFO Client 30680] @From buyer: Hi, I would like to buy your Tabula Rasa Simple Robe listed for 8 chaos in Ancestor (stash tab \"~price 8 chaos\"; position: left 1, top 1)";
+
+    fn temp_log() -> (PathBuf, String) {
+        let dir = std::env::temp_dir().join(format!("ingest-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("Client.txt");
+        std::fs::write(&log, b"").unwrap();
+        let logpath = log.to_string_lossy().into_owned();
+        (dir, logpath)
+    }
+
+    fn append(logpath: &str, line: &str) {
+        let mut f = std::fs::OpenOptions::new()
+            .append(true)
+            .open(logpath)
+            .unwrap();
+        writeln!(f, "{}", line).unwrap();
+    }
+
+    // model publishing names of new incoming trades
+    fn model(seen: Sender<(String, Instant)>) -> ModelHandle {
+        ModelHandle::spawn(Model::new(), move |ev| {
+            if let ModelEvent::NewIncoming(t) = ev.data {
+                seen.send((t.player_name().to_string(), Instant::now()))
+                    .unwrap();
+            }
+        })
+    }
+
+    #[test]
+    fn trades_arrive_without_debounce_delay() {
+        let (dir, logpath) = temp_log();
+        let (seen_tx, seen_rx) = channel();
+
+        let (tx, rx) = channel();
+        let _watcher = watch_log(tx, &logpath).unwrap();
+        let backfill = BackfillSettings::default();
+        let ingest = LogIngest::new(model(seen_tx), &logpath, &backfill);
+        let handle = IngestHandle::spawn(ingest, rx, |_| {});
+
+        let written = Instant::now();
+        append(&logpath, TRADE);
+        let (_, seen) = seen_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        // debouncer used to hold every change for 300 ms, bound leaves room for loaded machines
+        assert!(seen - written < Duration::from_secs(2));
+
+        // batch is recorded once the model is done with it
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let stats = loop {
+            let stats = handle.with(|i| i.stats());
+            if stats.batches > 0 || Instant::now() > deadline {
+                break stats;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!((stats.batches, stats.lines, stats.restarts), (1, 1, 0));
+        assert!(stats.p95_ms.is_some());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    // reading a directory fails, unlike opening it
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reader_restarts_after_failed_read() {
+        let (dir, logpath) = temp_log();
+        let (seen_tx, seen_rx) = channel();
+        let (tx, rx) = channel();
+        let backfill = BackfillSettings::default();
+        let ingest = LogIngest::new(model(seen_tx), &logpath, &backfill);
+        let handle = IngestHandle::spawn(ingest, rx, |_| {});
+        let changed = || tx.send(Ok(vec![PathBuf::from(&logpath)])).unwrap();
+
+        std::fs::remove_file(&logpath).unwrap();
+        std::fs::create_dir(&logpath).unwrap();
+        changed();
+        std::thread::sleep(RESTART_DELAY * 2);
+        assert_eq!(handle.with(|i| i.stats()).restarts, 1);
+
+        std::fs::remove_dir(&logpath).unwrap();
+        std::fs::write(&logpath, b"").unwrap();
+        changed();
+        append(&logpath, TRADE);
+        changed();
+        let (name, _) = seen_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(name, "buyer");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn trade_written_while_restarting_is_read() {
+        let (dir, logpath) = temp_log();
+        let (seen_tx, seen_rx) = channel();
+        let backfill = BackfillSettings {
+            minutes: 0,
+            ..Default::default()
+        };
+        let mut ingest = LogIngest::new(model(seen_tx), &logpath, &backfill);
+        let paths = [PathBuf::from(&logpath)];
+        append(&logpath, &TRADE.replace("buyer", "first"));
+        ingest.handle(&paths).unwrap();
+        assert_eq!(
+            seen_rx.recv_timeout(Duration::from_secs(5)).unwrap().0,
+            "first"
+        );
+
+        // read failed before seeing the trade, not even backfill would bring it back
+        append(&logpath, TRADE);
+        ingest.restart();
+        ingest.handle(&paths).unwrap();
+        assert_eq!(
+            seen_rx.recv_timeout(Duration::from_secs(5)).unwrap().0,
+            "buyer"
+        );
+
+        // replaced log is backfilled
+        std::fs::remove_file(&logpath).unwrap();
+        let now = chrono::Local::now().naive_local();
+        let line = TRADE.replace(
+            "2023/10/13 01:54:50",
+            &now.format("%Y/%m/%d %H:%M:%S").to_string(),
+        );
+        std::fs::write(&logpath, format!("{}\n", line.replace("buyer", "other"))).unwrap();
+        ingest.backfill.minutes = 5;
+        ingest.restart();
+        assert_eq!(
+            seen_rx.recv_timeout(Duration::from_secs(5)).unwrap().0,
+            "other"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn latency_percentiles() {
+        let mut metrics = Metrics::default();
+        assert_eq!(metrics.stats().p50_ms, None);
+        for ms in 1..=100 {
+            metrics.record(1, Some(Duration::from_millis(ms)));
+        }
+        metrics.record(2, None);
+        let stats = metrics.stats();
+        assert_eq!((stats.batches, stats.lines), (101, 102));
+        assert_eq!(stats.last_ms, Some(100.0));
+        assert_eq!(stats.p50_ms, Some(51.0));
+        assert_eq!(stats.p95_ms, Some(96.0));
+        assert_eq!(stats.max_ms, Some(100.0));
+    }
+}
@@ -0,0 +1,257 @@
+// contract between the app and its webviews: event names, commands with their
+// arguments and every payload type; src/lib/ipc.ts is generated from it by
+// `UPDATE_IPC=1 cargo test -p trade-core --test ipc`
+use crate::classifier::FollowUpIntent;
+use crate::command_queue::QueueState;
+use crate::commands::{ChatCommand, TradeAction};
+use crate::discovery::{Candidate, CandidateSource};
+use crate::hotkeys::{HotkeyConflict, Hotkeys};
+use crate::ingest::IngestStats;
+use crate::log_watcher::{WatchState, WatchStatus, WatcherMode, WatcherSettings};
+use crate::macros::{ChatMacro, MacroStep};
+use crate::model::{PurchaseIntent, TradeInfo, TradeStatus, TradeType};
+use crate::model_actor::{TradesSnapshot, Versioned};
+use crate::overlay::OverlaySettings;
+use ts_rs::TS;
+
+// bumped whenever a payload, command or event changes, the bindings snapshot test
+// fails until it is
+pub const IPC_VERSION: u32 = 6;
+
+pub const NEW_INCOMING_TRADE: &str = "new-incoming-trade";
+pub const NEW_OUTGOING_TRADE: &str = "new-outgoing-trade";
+pub const OUTGOING_INTENT_UPDATED: &str = "outgoing-intent-updated";
+pub const TRADE_CLOSED: &str = "trade-closed";
+pub const INCOMING_TRADE_SELECTED: &str = "incoming-trade-selected";
+pub const COMMAND_QUEUE_STATE: &str = "command-queue-state";
+pub const HOTKEY_CONFLICTS: &str = "hotkey-conflicts";
+pub const LOG_WATCHER_STATUS: &str = "log-watcher-status";
+pub const OVERLAY_SETTINGS: &str = "overlay-settings";
+pub const SETTINGS_CHANGED: &str = "settings-changed";
+pub const SETTINGS_SAVE_FAILED: &str = "settings-save-failed";
+
+// LogPathError is serialized by hand, so is its declaration
+const LOG_PATH_ERROR: &str = r#"type LogPathError = { kind: "empty" | "notFound" | "notAFile" | "unreadable" | "notClientLog", message: string, };"#;
+
+pub struct Event {
+    pub name: &'static str,
+    pub payload: String,
+}
+
+impl Event {
+    fn new<T: TS>(name: &'static str) -> Self {
+        Event {
+            name,
+            payload: T::name(),
+        }
+    }
+}
+
+pub struct Command {
+    pub name: &'static str,
+    // names as in the handler, tauri takes them in camelCase
+    pub args: Vec<(&'static str, String)>,
+    pub result: String,
+    // what the invoke promise is rejected with
+    pub error: String,
+}
+
+impl Command {
+    fn new<R: TS>(name: &'static str) -> Self {
+        Command {
+            name,
+            args: vec![],
+            result: R::name(),
+            error: "never".to_string(),
+        }
+    }
+
+    fn arg<T: TS>(mut self, name: &'static str) -> Self {
+        self.args.push((name, T::name()));
+        self
+    }
+
+    fn error(mut self, ts: &str) -> Self {
+        self.error = ts.to_string();
+        self
+    }
+}
+
+pub fn events() -> Vec<Event> {
+    vec![
+        Event::new::<Versioned<TradeInfo>>(NEW_INCOMING_TRADE),
+        Event::new::<Versioned<TradeInfo>>(NEW_OUTGOING_TRADE),
+        Event::new::<Versioned<PurchaseIntent>>(OUTGOING_INTENT_UPDATED),
+        Event::new::<Versioned<String>>(TRADE_CLOSED),
+        Event::new::<String>(INCOMING_TRADE_SELECTED),
+        Event::new::<QueueState>(COMMAND_QUEUE_STATE),
+        Event::new::<Vec<HotkeyConflict>>(HOTKEY_CONFLICTS),
+        Event::new::<WatchStatus>(LOG_WATCHER_STATUS),
+        Event::new::<OverlaySettings>(OVERLAY_SETTINGS),
+        Event::new::<Vec<String>>(SETTINGS_CHANGED),
+        Event::new::<String>(SETTINGS_SAVE_FAILED),
+    ]
+}
+
+// everything registered with `generate_handler!` in the app
+pub fn commands() -> Vec<Command> {
+    vec![
+        Command::new::<()>("save_window_layout").arg::<String>("window"),
+        Command::new::<()>("update_logpath_stx")
+            .arg::<String>("logpath")
+            .error("LogPathError"),
+        Command::new::<Vec<Candidate>>("discover_logpaths"),
+        Command::new::<WatchStatus>("get_log_watcher_status"),
+        Command::new::<IngestStats>("get_ingest_stats"),
+        Command::new::<WatcherSettings>("get_watcher_stx"),
+        Command::new::<()>("update_watcher_stx").arg::<WatcherSettings>("watcher"),
+        Command::new::<OverlaySettings>("get_overlay_stx"),
+        Command::new::<()>("update_overlay_stx").arg::<OverlaySettings>("overlay"),
+        Command::new::<TradesSnapshot>("get_trades").error("string"),
+        Command::new::<()>("trade_close").arg::<String>("id"),
+        Command::new::<()>("trade_complete").arg::<String>("id"),
+        Command::new::<Option<PurchaseIntent>>("trade_decline").arg::<String>("id"),
+        Command::new::<Vec<ChatMacro>>("list_macros").arg::<TradeType>("trade_type"),
+        Command::new::<()>("run_macro")
+            .arg::<String>("id")
+            .arg::<String>("name")
+            .error("string"),
+        Command::new::<()>("trade_action")
+            .arg::<String>("id")
+            .arg::<TradeAction>("action")
+            .error("string"),
+        Command::new::<()>("select_trade").arg::<String>("id"),
+        Command::new::<Hotkeys>("get_hotkeys_stx"),
+        Command::new::<Vec<HotkeyConflict>>("update_hotkeys_stx").arg::<Hotkeys>("hotkeys"),
+        Command::new::<usize>("cancel_commands").arg::<Option<String>>("id"),
+        Command::new::<QueueState>("get_command_queue_state"),
+    ]
+}
+
+fn declarations() -> Vec<String> {
+    vec![
+        TradeType::decl(),
+        TradeStatus::decl(),
+        FollowUpIntent::decl(),
+        TradeInfo::decl(),
+        PurchaseIntent::decl(),
+        Versioned::<()>::decl(),
+        TradesSnapshot::decl(),
+        QueueState::decl(),
+        ChatCommand::decl(),
+        TradeAction::decl(),
+        MacroStep::decl(),
+        ChatMacro::decl(),
+        Hotkeys::decl(),
+        HotkeyConflict::decl(),
+        CandidateSource::decl(),
+        Candidate::decl(),
+        WatcherMode::decl(),
+        WatcherSettings::decl(),
+        WatchState::decl(),
+        WatchStatus::decl(),
+        IngestStats::decl(),
+        OverlaySettings::decl(),
+        LOG_PATH_ERROR.to_string(),
+    ]
+}
+
+pub(crate) fn camel_case(name: &str) -> String {
+    let mut parts = name.split('_');
+    let first = parts.next().unwrap_or_default().to_string();
+    parts.fold(first, |mut s, p| {
+        let mut chars = p.chars();
+        if let Some(c) = chars.next() {
+            s.extend(c.to_uppercase());
+            s.push_str(chars.as_str());
+        }
+        s
+    })
+}
+
+const HELPERS: &str = "export function invoke<C extends keyof Commands>(
+	cmd: C,
+	args?: Commands[C]['args']
+): Promise<Commands[C]['result']> {
+	return tauriInvoke(cmd, args);
+}
+
+export function listen<E extends keyof Events>(
+	event: E,
+	handler: EventCallback<Events[E]>
+): Promise<UnlistenFn> {
+	return tauriListen(event, handler);
+}
+
+export function emit<E extends keyof Events>(event: E, payload?: Events[E]): Promise<void> {
+	return tauriEmit(event, payload);
+}
+";
+
+// contents of src/lib/ipc.ts
+pub fn bindings() -> String {
+    let mut out = String::from(
+        "// generated from src-tauri/core/src/ipc.rs, do not edit; regenerate with\n\
+         // UPDATE_IPC=1 cargo test -p trade-core --test ipc\n\
+         import { invoke as tauriInvoke } from '@tauri-apps/api/tauri';\n\
+         import { emit as tauriEmit, listen as tauriListen } from '@tauri-apps/api/event';\n\
+         import type { EventCallback, UnlistenFn } from '@tauri-apps/api/event';\n\n",
+    );
+    out.push_str(&format!("export const IPC_VERSION = {};\n\n", IPC_VERSION));
+    for decl in declarations() {
+        out.push_str(&format!("export {}\n\n", decl));
+    }
+
+    out.push_str("export type Commands = {\n");
+    for c in commands() {
+        let args: Vec<String> = c
+            .args
+            .iter()
+            .map(|(name, ts)| format!("{}: {}", camel_case(name), ts))
+            .collect();
+        let args = if args.is_empty() {
+            "Record<string, never>".to_string()
+        } else {
+            format!("{{ {} }}", args.join("; "))
+        };
+        out.push_str(&format!(
+            "\t{}: {{ args: {}; result: {}; error: {} }};\n",
+            c.name, args, c.result, c.error
+        ));
+    }
+    out.push_str("};\n\nexport type Events = {\n");
+    for e in events() {
+        out.push_str(&format!("\t'{}': {};\n", e.name, e.payload));
+    }
+    out.push_str("};\n\n");
+    out.push_str(HELPERS);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_watcher::LogPathError;
+
+    #[test]
+    fn command_args_are_camel_case() {
+        assert_eq!(camel_case("trade_type"), "tradeType");
+        assert_eq!(camel_case("id"), "id");
+        assert!(bindings()
+            .contains("list_macros: { args: { tradeType: TradeType }; result: Array<ChatMacro>;"));
+    }
+
+    #[test]
+    fn log_path_error_kinds_are_declared() {
+        let errors = [
+            LogPathError::Empty,
+            LogPathError::NotFound(String::new()),
+            LogPathError::NotAFile(String::new()),
+            LogPathError::Unreadable(String::new(), String::new()),
+            LogPathError::NotClientLog(String::new()),
+        ];
+        for e in errors.iter() {
+            assert!(LOG_PATH_ERROR.contains(&format!("\"{}\"", e.kind())));
+        }
+    }
+}
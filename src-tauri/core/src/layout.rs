@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// monitor as the window system reports it, position and size in physical pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    pub name: Option<String>,
+    pub position: (i32, i32),
+    pub size: (u32, u32),
+    pub scale: f64,
+}
+
+impl Monitor {
+    fn right(&self) -> i64 {
+        self.position.0 as i64 + self.size.0 as i64
+    }
+
+    fn bottom(&self) -> i64 {
+        self.position.1 as i64 + self.size.1 as i64
+    }
+
+    fn contains(&self, (x, y): (i64, i64)) -> bool {
+        x >= self.position.0 as i64
+            && x < self.right()
+            && y >= self.position.1 as i64
+            && y < self.bottom()
+    }
+
+    fn overlap(&self, p: &Placement) -> i64 {
+        let w = self.right().min(p.right()) - (self.position.0 as i64).max(p.position.0 as i64);
+        let h = self.bottom().min(p.bottom()) - (self.position.1 as i64).max(p.position.1 as i64);
+        w.max(0) * h.max(0)
+    }
+}
+
+// where a window is, in physical pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    pub position: (i32, i32),
+    pub size: (u32, u32),
+}
+
+impl Placement {
+    fn right(&self) -> i64 {
+        self.position.0 as i64 + self.size.0 as i64
+    }
+
+    fn bottom(&self) -> i64 {
+        self.position.1 as i64 + self.size.1 as i64
+    }
+
+    fn center(&self) -> (i64, i64) {
+        (
+            self.position.0 as i64 + self.size.0 as i64 / 2,
+            self.position.1 as i64 + self.size.1 as i64 / 2,
+        )
+    }
+}
+
+// window saved in logical pixels relative to its monitor, so it lands in the same spot
+// when the monitor is moved around or its scaling changes
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct WindowLayout {
+    pub monitor: Option<String>,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+// identifies a set of monitors, layouts are kept for each one the user had
+pub fn config_key(monitors: &[Monitor]) -> String {
+    let mut parts: Vec<String> = monitors
+        .iter()
+        .map(|m| {
+            format!(
+                "{}:{}x{}@{},{}",
+                m.name.as_deref().unwrap_or("?"),
+                m.size.0,
+                m.size.1,
+                m.position.0,
+                m.position.1
+            )
+        })
+        .collect();
+    parts.sort();
+    parts.join(";")
+}
+
+// monitor the window is mostly on, the first one when it is on none
+fn monitor_of<'a>(monitors: &'a [Monitor], p: &Placement) -> Option<&'a Monitor> {
+    monitors
+        .iter()
+        .find(|m| m.contains(p.center()))
+        .or_else(|| {
+            monitors
+                .iter()
+                .filter(|m| m.overlap(p) > 0)
+                .max_by_key(|m| m.overlap(p))
+        })
+        .or_else(|| monitors.first())
+}
+
+pub fn capture(monitors: &[Monitor], p: &Placement) -> WindowLayout {
+    let (origin, scale, name) = match monitor_of(monitors, p) {
+        Some(m) => (m.position, m.scale, m.name.clone()),
+        None => ((0, 0), 1.0, None),
+    };
+    WindowLayout {
+        monitor: name,
+        x: (p.position.0 - origin.0) as f64 / scale,
+        y: (p.position.1 - origin.1) as f64 / scale,
+        width: p.size.0 as f64 / scale,
+        height: p.size.1 as f64 / scale,
+    }
+}
+
+pub fn restore(monitors: &[Monitor], layout: &WindowLayout) -> Placement {
+    let monitor = monitors
+        .iter()
+        .find(|m| layout.monitor.is_some() && m.name == layout.monitor)
+        .or_else(|| monitors.first());
+    let (origin, scale) = match monitor {
+        Some(m) => (m.position, m.scale),
+        None => ((0, 0), 1.0),
+    };
+    Placement {
+        position: (
+            origin.0 + (layout.x * scale).round() as i32,
+            origin.1 + (layout.y * scale).round() as i32,
+        ),
+        size: (
+            (layout.width * scale).round() as u32,
+            (layout.height * scale).round() as u32,
+        ),
+    }
+}
+
+// moves a window that is not fully on a monitor back onto the one it is mostly on,
+// shrinking it if it doesn't fit
+pub fn clamp(monitors: &[Monitor], p: Placement) -> Placement {
+    let m = match monitor_of(monitors, &p) {
+        Some(m) => m,
+        None => return p,
+    };
+    let size = (p.size.0.min(m.size.0), p.size.1.min(m.size.1));
+    let fit = |pos: i32, len: u32, start: i32, end: i64| -> i32 {
+        (pos as i64).min(end - len as i64).max(start as i64) as i32
+    };
+    Placement {
+        position: (
+            fit(p.position.0, size.0, m.position.0, m.right()),
+            fit(p.position.1, size.1, m.position.1, m.bottom()),
+        ),
+        size,
+    }
+}
+
+// window layouts by monitor configuration and window label
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Layouts(BTreeMap<String, BTreeMap<String, WindowLayout>>);
+
+impl Layouts {
+    pub fn save(&mut self, monitors: &[Monitor], label: &str, p: &Placement) {
+        self.0
+            .entry(config_key(monitors))
+            .or_default()
+            .insert(label.to_string(), capture(monitors, p));
+    }
+
+    // saved placement for the current monitors, clamped onto them
+    pub fn placement(&self, monitors: &[Monitor], label: &str) -> Option<Placement> {
+        let layout = self.0.get(&config_key(monitors))?.get(label)?;
+        Some(clamp(monitors, restore(monitors, layout)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, position: (i32, i32), size: (u32, u32), scale: f64) -> Monitor {
+        Monitor {
+            name: Some(name.to_string()),
+            position,
+            size,
+            scale,
+        }
+    }
+
+    fn at(position: (i32, i32), size: (u32, u32)) -> Placement {
+        Placement { position, size }
+    }
+
+    #[test]
+    fn layouts_survive_scaling_and_rearranging() {
+        let laptop = monitor("eDP-1", (0, 0), (2880, 1800), 2.0);
+        let alone = [laptop.clone()];
+        let mut layouts = Layouts::default();
+        layouts.save(&alone, "incoming", &at((200, 100), (870, 640)));
+        let saved = &layouts.0[&config_key(&alone)]["incoming"];
+        assert_eq!(
+            (saved.x, saved.y, saved.width, saved.height),
+            (100.0, 50.0, 435.0, 320.0)
+        );
+
+        // same monitor at 150%
+        let scaled = Monitor {
+            scale: 1.5,
+            ..laptop.clone()
+        };
+        assert_eq!(
+            layouts.placement(&[scaled], "incoming"),
+            Some(at((150, 75), (653, 480)))
+        );
+        // another monitor configuration has layouts of its own
+        let external = monitor("HDMI-1", (2880, 0), (1920, 1080), 1.0);
+        assert_eq!(layouts.placement(&[laptop, external], "incoming"), None);
+    }
+
+    #[test]
+    fn windows_on_second_monitor_are_relative_to_it() {
+        let left = monitor("DP-1", (-1920, 0), (1920, 1080), 1.0);
+        let right = monitor("DP-2", (0, 0), (2560, 1440), 1.25);
+        let monitors = [left.clone(), right];
+        let layout = capture(&monitors, &at((-1800, 100), (435, 320)));
+        assert_eq!(layout.monitor.as_deref(), Some("DP-1"));
+        assert_eq!((layout.x, layout.y), (120.0, 100.0));
+
+        // left monitor moved to the other side
+        let moved = Monitor {
+            position: (2560, 0),
+            ..left
+        };
+        assert_eq!(restore(&[moved], &layout), at((2680, 100), (435, 320)));
+    }
+
+    #[test]
+    fn off_screen_windows_are_clamped() {
+        let main = monitor("DP-1", (0, 0), (1920, 1080), 1.0);
+        let monitors = [main];
+        // position from an unplugged monitor on the right
+        assert_eq!(
+            clamp(&monitors, at((2500, 300), (435, 320))),
+            at((1485, 300), (435, 320))
+        );
+        // partly above the top edge
+        assert_eq!(
+            clamp(&monitors, at((100, -50), (435, 320))),
+            at((100, 0), (435, 320))
+        );
+        // larger than the monitor
+        assert_eq!(
+            clamp(&monitors, at((-10, -10), (4000, 300))),
+            at((0, 0), (1920, 300))
+        );
+        // visible windows stay where they are
+        assert_eq!(
+            clamp(&monitors, at((10, 10), (435, 320))),
+            at((10, 10), (435, 320))
+        );
+    }
+}
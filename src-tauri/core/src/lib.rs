@@ -0,0 +1,30 @@
+// trade engine of the companion: Client.txt reading, trade model and chat commands,
+// the tauri app and other tools are thin shells over it
+pub mod classifier;
+pub mod command_queue;
+pub mod commands;
+pub mod discovery;
+pub mod file_line_reader;
+pub mod history;
+pub mod hotkeys;
+pub mod ingest;
+pub mod ipc;
+pub mod layout;
+pub mod log_watcher;
+pub mod macros;
+pub mod model;
+pub mod model_actor;
+pub mod overlay;
+pub mod parser;
+pub mod replay;
+pub mod settings;
+#[cfg(test)]
+mod test_utilities;
+#[cfg(target_os = "linux")]
+pub mod wayland_dispatcher;
+#[cfg(target_os = "linux")]
+pub mod x11_dispatcher;
+
+pub use file_line_reader::{BackfillSettings, FileLineReader, LineFramer, LogFile};
+pub use model::{Model, ModelError, PurchaseIntent, TradeInfo, TradeStatus, TradeType};
+pub use settings::Settings;
@@ -0,0 +1,690 @@
+use crate::model::line_time;
+use log::{debug, error};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Result, Watcher};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc,
+    },
+    time::Duration,
+};
+use ts_rs::TS;
+
+// paths changed in the watched directory, or failure of the watcher
+pub type LogEvent = Result<Vec<PathBuf>>;
+
+// raw change notifications delivered as they come, reading the log shows up as access
+// events which are dropped so the reader doesn't wake itself up
+pub fn new_watcher<F>(mut handler: F) -> Result<RecommendedWatcher>
+where
+    F: FnMut(LogEvent) + Send + 'static,
+{
+    notify::recommended_watcher(move |res: Result<Event>| match res {
+        Ok(e) if matches!(e.kind, EventKind::Access(_)) => {}
+        Ok(e) => handler(Ok(e.paths)),
+        Err(e) => handler(Err(e)),
+    })
+}
+
+// watcher sending changes of directory of the log to `tx`
+pub fn watch_log(tx: Sender<LogEvent>, logpath: &str) -> Result<RecommendedWatcher> {
+    let mut watcher = new_watcher(move |ev| {
+        let _ = tx.send(ev);
+    })?;
+    watcher.watch(&watch_dir(logpath), RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
+
+// directory of the log is watched so deleting and recreating the log keeps events coming
+pub fn watch_dir(logpath: &str) -> PathBuf {
+    match Path::new(logpath).parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+// other files in the watched directory are skipped
+pub fn touches_log(paths: &[PathBuf], logpath: &str) -> bool {
+    let name = Path::new(logpath).file_name();
+    paths.iter().any(|p| p.file_name() == name)
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum WatcherMode {
+    // change notifications, switches to polling when they can't be set up or stay silent
+    #[default]
+    Auto,
+    Notify,
+    // for wine prefixes, network shares and fuse mounts that never send events
+    Poll,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct WatcherSettings {
+    #[serde(default)]
+    pub mode: WatcherMode,
+    // how often size of the log is checked when polling
+    #[serde(default = "default_poll_interval_ms")]
+    #[ts(type = "number")]
+    pub poll_interval_ms: u64,
+}
+
+fn default_poll_interval_ms() -> u64 {
+    1000
+}
+
+impl Default for WatcherSettings {
+    fn default() -> Self {
+        WatcherSettings {
+            mode: WatcherMode::default(),
+            poll_interval_ms: default_poll_interval_ms(),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum LogPathError {
+    #[error("log path is empty")]
+    Empty,
+    #[error("{0} does not exist")]
+    NotFound(String),
+    #[error("{0} is not a file")]
+    NotAFile(String),
+    #[error("can't read {0}: {1}")]
+    Unreadable(String, String),
+    #[error("{0} doesn't look like a game client log")]
+    NotClientLog(String),
+}
+
+impl LogPathError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LogPathError::Empty => "empty",
+            LogPathError::NotFound(_) => "notFound",
+            LogPathError::NotAFile(_) => "notAFile",
+            LogPathError::Unreadable(_, _) => "unreadable",
+            LogPathError::NotClientLog(_) => "notClientLog",
+        }
+    }
+}
+
+// sent to the frontend as `{ kind, message }`
+impl Serialize for LogPathError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("LogPathError", 2)?;
+        s.serialize_field("kind", self.kind())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.end()
+    }
+}
+
+// head of a log looked at when validating it
+const VALIDATE_BYTES: usize = 16 * 1024;
+const VALIDATE_LINES: usize = 20;
+
+// client messages look like `2023/10/13 01:54:41 1054471421 cffb0719 [INFO Client 30680] ...`
+static CLIENT_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\d{4}/\d\d/\d\d \d\d:\d\d:\d\d \d+ \w+ \[[A-Z]+ Client \d+\]").unwrap()
+});
+
+// file exists, can be read and its first lines are the game's: all timestamped, with
+// client messages or only the marker the game writes when it opens the log; an empty
+// file is fine since the game truncates it sometimes
+pub fn validate_log(logpath: &str) -> std::result::Result<(), LogPathError> {
+    if logpath.trim().is_empty() {
+        return Err(LogPathError::Empty);
+    }
+    let meta = std::fs::metadata(logpath).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => LogPathError::NotFound(logpath.to_string()),
+        _ => LogPathError::Unreadable(logpath.to_string(), e.to_string()),
+    })?;
+    if !meta.is_file() {
+        return Err(LogPathError::NotAFile(logpath.to_string()));
+    }
+
+    let mut head = vec![0; VALIDATE_BYTES];
+    let read = std::fs::File::open(logpath)
+        .and_then(|mut f| f.read(&mut head))
+        .map_err(|e| LogPathError::Unreadable(logpath.to_string(), e.to_string()))?;
+    let head = String::from_utf8_lossy(&head[..read]);
+    let mut lines: Vec<&str> = head.lines().collect();
+    // last line may be cut off by the buffer
+    if read == VALIDATE_BYTES && lines.len() > 1 {
+        lines.pop();
+    }
+    let lines: Vec<&str> = lines
+        .into_iter()
+        .filter(|l| !l.trim().is_empty())
+        .take(VALIDATE_LINES)
+        .collect();
+    if lines.is_empty() {
+        return Ok(());
+    }
+
+    let timestamped = lines.iter().all(|l| line_time(l).is_some());
+    let client = lines.iter().any(|l| CLIENT_LINE.is_match(l));
+    let opened = lines
+        .iter()
+        .all(|l| l.ends_with("***** LOG FILE OPENING *****"));
+    if timestamped && (client || opened) {
+        Ok(())
+    } else {
+        Err(LogPathError::NotClientLog(logpath.to_string()))
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum WatchState {
+    Idle,
+    Watching,
+    // retried in background
+    Failed,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchStatus {
+    pub state: WatchState,
+    pub path: String,
+    pub error: Option<String>,
+    // changes are found by polling instead of notifications
+    pub polling: bool,
+}
+
+// decides from consecutive size checks whether notifications missed a change
+#[derive(Debug, Default)]
+struct SilenceCheck {
+    pending: bool,
+}
+
+impl SilenceCheck {
+    // notify gets a whole interval to report a change seen on previous check,
+    // flag is cleared only while the log is quiet so reports that come early aren't lost
+    fn missed(&mut self, changed: bool, notified: &AtomicBool) -> bool {
+        let missed = if self.pending {
+            !notified.swap(false, Ordering::Relaxed)
+        } else {
+            if !changed {
+                notified.store(false, Ordering::Relaxed);
+            }
+            false
+        };
+        self.pending = changed;
+        missed
+    }
+}
+
+// checks size of the log on an interval and sends the same events as notify would,
+// as a probe only forwards changes once notify is found to miss them
+struct Poller {
+    stop: Arc<AtomicBool>,
+    forwarding: Arc<AtomicBool>,
+}
+
+impl Poller {
+    fn spawn(
+        logpath: &str,
+        interval: Duration,
+        tx: Sender<LogEvent>,
+        notified: Arc<AtomicBool>,
+        forwarding: bool,
+    ) -> Self {
+        let poller = Poller {
+            stop: Arc::new(AtomicBool::new(false)),
+            forwarding: Arc::new(AtomicBool::new(forwarding)),
+        };
+        let stop = Arc::clone(&poller.stop);
+        let forwarding = Arc::clone(&poller.forwarding);
+        let path = PathBuf::from(logpath);
+        let size = |p: &Path| std::fs::metadata(p).map(|m| m.len()).ok();
+        // taken before returning so changes right after watching aren't missed
+        let mut last = size(&path);
+        std::thread::spawn(move || {
+            let mut check = SilenceCheck::default();
+            loop {
+                std::thread::sleep(interval);
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let current = size(&path);
+                let changed = current != last;
+                last = current;
+
+                let send = if forwarding.load(Ordering::Relaxed) {
+                    changed
+                } else if check.missed(changed, &notified) {
+                    debug!("notify missed a change of {}, polling", path.display());
+                    forwarding.store(true, Ordering::Relaxed);
+                    true
+                } else {
+                    false
+                };
+                if !send {
+                    continue;
+                }
+                if tx.send(Ok(vec![path.clone()])).is_err() {
+                    break;
+                }
+            }
+        });
+        poller
+    }
+
+    fn forwarding(&self) -> bool {
+        self.forwarding.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// keeps directory of the log watched, failures are reported and fixed by `retry`
+pub struct LogWatcher {
+    tx: Sender<LogEvent>,
+    settings: WatcherSettings,
+    // none when change notifications can't be set up, the log is polled then
+    watcher: Option<RecommendedWatcher>,
+    notify_error: Option<String>,
+    watched: Option<PathBuf>,
+    poller: Option<Poller>,
+    // set when notify reports anything in the watched directory
+    notified: Arc<AtomicBool>,
+    status: WatchStatus,
+    status_callback: Box<dyn Fn(&WatchStatus) + Send>,
+}
+
+impl LogWatcher {
+    pub fn new(tx: Sender<LogEvent>, settings: WatcherSettings) -> Result<Self> {
+        let notified = Arc::new(AtomicBool::new(false));
+        let watcher = {
+            let tx = tx.clone();
+            let notified = Arc::clone(&notified);
+            new_watcher(move |res| {
+                if res.is_ok() {
+                    notified.store(true, Ordering::Relaxed);
+                }
+                let _ = tx.send(res);
+            })?
+        };
+        Ok(Self::create(tx, settings, Some(watcher), notified, None))
+    }
+
+    // change notifications failed to set up, the log is polled whatever the mode
+    // and the failure is reported with the status
+    pub fn polling_only(tx: Sender<LogEvent>, settings: WatcherSettings, error: String) -> Self {
+        Self::create(
+            tx,
+            settings,
+            None,
+            Arc::new(AtomicBool::new(false)),
+            Some(error),
+        )
+    }
+
+    fn create(
+        tx: Sender<LogEvent>,
+        settings: WatcherSettings,
+        watcher: Option<RecommendedWatcher>,
+        notified: Arc<AtomicBool>,
+        notify_error: Option<String>,
+    ) -> Self {
+        LogWatcher {
+            tx,
+            settings,
+            watcher,
+            notify_error,
+            watched: None,
+            poller: None,
+            notified,
+            status: WatchStatus {
+                state: WatchState::Idle,
+                path: String::new(),
+                error: None,
+                polling: false,
+            },
+            status_callback: Box::new(|_| {}),
+        }
+    }
+
+    pub fn status_subscribe<F>(&mut self, cb: F)
+    where
+        F: Fn(&WatchStatus) + Send + 'static,
+    {
+        self.status_callback = Box::new(cb);
+    }
+
+    pub fn status(&self) -> WatchStatus {
+        self.status.clone()
+    }
+
+    pub fn watch(&mut self, logpath: &str) {
+        self.unwatch();
+        self.poller = None;
+        self.status.path = logpath.to_string();
+        self.try_watch();
+    }
+
+    // switches mode at runtime, the current log is watched again
+    pub fn set_settings(&mut self, settings: WatcherSettings) {
+        self.settings = settings;
+        let logpath = self.status.path.clone();
+        self.watch(&logpath);
+    }
+
+    // watcher stopped delivering events, it's set up again on next retry
+    // unless polling can take over
+    pub fn fail(&mut self, error: String) {
+        error!("watcher of {} failed: {}", self.status.path, error);
+        self.unwatch();
+        match self.poller.as_ref() {
+            Some(p) => {
+                p.forwarding.store(true, Ordering::Relaxed);
+                self.set_status(WatchState::Watching, None);
+            }
+            None => self.set_status(WatchState::Failed, Some(error)),
+        }
+    }
+
+    // called periodically, also stops notify once the poller took over
+    pub fn retry(&mut self) {
+        match self.status.state {
+            WatchState::Failed => {
+                debug!("retrying to watch {}", self.status.path);
+                self.try_watch();
+            }
+            WatchState::Watching if self.polling() && self.watched.is_some() => {
+                self.unwatch();
+                self.set_status(WatchState::Watching, None);
+            }
+            _ => {}
+        }
+    }
+
+    fn polling(&self) -> bool {
+        matches!(&self.poller, Some(p) if p.forwarding())
+    }
+
+    fn unwatch(&mut self) {
+        if let (Some(dir), Some(watcher)) = (self.watched.take(), self.watcher.as_mut()) {
+            let _ = watcher.unwatch(&dir);
+        }
+    }
+
+    fn try_watch(&mut self) {
+        if self.status.path.is_empty() {
+            self.set_status(WatchState::Idle, None);
+            return;
+        }
+        let polling = match self.watcher.as_mut() {
+            Some(_) if self.settings.mode == WatcherMode::Poll => true,
+            None => true,
+            Some(watcher) => {
+                let dir = watch_dir(&self.status.path);
+                match watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                    Ok(_) => {
+                        self.watched = Some(dir);
+                        false
+                    }
+                    Err(e) if self.settings.mode == WatcherMode::Auto => {
+                        debug!("can't watch {}, polling: {}", self.status.path, e);
+                        true
+                    }
+                    Err(e) => {
+                        error!("can't watch {}: {}", self.status.path, e);
+                        self.set_status(WatchState::Failed, Some(e.to_string()));
+                        return;
+                    }
+                }
+            }
+        };
+        if self.settings.mode != WatcherMode::Notify || self.watcher.is_none() {
+            self.notified.store(false, Ordering::Relaxed);
+            self.poller = Some(Poller::spawn(
+                &self.status.path,
+                Duration::from_millis(self.settings.poll_interval_ms.max(1)),
+                self.tx.clone(),
+                Arc::clone(&self.notified),
+                polling,
+            ));
+        }
+        self.set_status(WatchState::Watching, self.notify_error.clone());
+    }
+
+    fn set_status(&mut self, state: WatchState, error: Option<String>) {
+        let polling = self.polling();
+        let changed = self.status.state != state
+            || self.status.error != error
+            || self.status.polling != polling;
+        self.status.state = state;
+        self.status.error = error;
+        self.status.polling = polling;
+        if changed {
+            (self.status_callback)(&self.status);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{mpsc::channel, Arc, Mutex};
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("watcher-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn log_validation() {
+        let dir = temp_dir();
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        std::fs::write(path("Client.txt"), b"2023/10/13 01:54:40 ***** LOG FILE OPENING *****\r\n2023/10/13 01:54:41 1 a [INFO Client 1] hi\r\n").unwrap();
+        std::fs::write(path("empty.txt"), b"").unwrap();
+        std::fs::write(
+            path("opened.txt"),
+            b"2023/10/13 01:54:40 ***** LOG FILE OPENING *****\r\n",
+        )
+        .unwrap();
+        std::fs::write(path("notes.txt"), b"shopping list\n").unwrap();
+        std::fs::write(
+            path("server.log"),
+            b"2023/10/13 01:54:40 server started\n2023/10/13 01:54:41 listening on 8080\n",
+        )
+        .unwrap();
+
+        assert_eq!(validate_log(&path("Client.txt")), Ok(()));
+        // empty or freshly rotated log of the game
+        assert_eq!(validate_log(&path("empty.txt")), Ok(()));
+        assert_eq!(validate_log(&path("opened.txt")), Ok(()));
+        assert_eq!(validate_log(""), Err(LogPathError::Empty));
+        assert_eq!(
+            validate_log(&path("Clent.txt")),
+            Err(LogPathError::NotFound(path("Clent.txt")))
+        );
+        assert_eq!(
+            validate_log(&dir.to_string_lossy()),
+            Err(LogPathError::NotAFile(dir.to_string_lossy().into_owned()))
+        );
+        assert_eq!(
+            validate_log(&path("notes.txt")),
+            Err(LogPathError::NotClientLog(path("notes.txt")))
+        );
+        // timestamped, but not by the game client
+        assert_eq!(
+            validate_log(&path("server.log")),
+            Err(LogPathError::NotClientLog(path("server.log")))
+        );
+        // a file standing in for a directory can't be looked into
+        #[cfg(unix)]
+        assert!(matches!(
+            validate_log(&path("notes.txt/Client.txt")),
+            Err(LogPathError::Unreadable(_, _))
+        ));
+
+        assert_eq!(
+            serde_json::to_value(LogPathError::Empty).unwrap(),
+            serde_json::json!({"kind": "empty", "message": "log path is empty"})
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failed_watch_is_retried() {
+        let (tx, _rx) = channel();
+        let settings = WatcherSettings {
+            mode: WatcherMode::Notify,
+            ..Default::default()
+        };
+        let mut watcher = LogWatcher::new(tx, settings).unwrap();
+        let states = Arc::new(Mutex::new(vec![]));
+        {
+            let states = Arc::clone(&states);
+            watcher.status_subscribe(move |s| states.lock().unwrap().push(s.state.clone()));
+        }
+
+        let dir = std::env::temp_dir().join(format!("watcher-{}", uuid::Uuid::new_v4()));
+        let logpath = dir.join("Client.txt").to_string_lossy().into_owned();
+        watcher.watch(&logpath);
+        assert_eq!(watcher.status().state, WatchState::Failed);
+        assert!(watcher.status().error.is_some());
+
+        // nothing changes until the directory shows up
+        watcher.retry();
+        std::fs::create_dir_all(&dir).unwrap();
+        watcher.retry();
+        assert_eq!(watcher.status().state, WatchState::Watching);
+        assert_eq!(watcher.status().path, logpath);
+
+        watcher.fail("events lost".to_string());
+        watcher.retry();
+        watcher.watch("");
+        assert_eq!(
+            *states.lock().unwrap(),
+            vec![
+                WatchState::Failed,
+                WatchState::Watching,
+                WatchState::Failed,
+                WatchState::Watching,
+                WatchState::Idle,
+            ]
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn polled(poll_interval_ms: u64) -> WatcherSettings {
+        WatcherSettings {
+            mode: WatcherMode::Poll,
+            poll_interval_ms,
+        }
+    }
+
+    fn next_event(rx: &std::sync::mpsc::Receiver<LogEvent>) -> PathBuf {
+        let paths = rx.recv_timeout(Duration::from_secs(5)).unwrap().unwrap();
+        paths[0].clone()
+    }
+
+    #[test]
+    fn polling_reports_size_changes() {
+        let dir = temp_dir();
+        let logpath = dir.join("Client.txt");
+        std::fs::write(&logpath, b"").unwrap();
+
+        let (tx, rx) = channel();
+        let mut watcher = LogWatcher::new(tx, polled(10)).unwrap();
+        watcher.watch(&logpath.to_string_lossy());
+        assert_eq!(watcher.status().state, WatchState::Watching);
+        assert!(watcher.status().polling);
+
+        std::fs::write(&logpath, b"2023/10/13 01:54:40 hi\n").unwrap();
+        assert_eq!(next_event(&rx), logpath);
+
+        // switched back to notifications at runtime, poller stops sending
+        watcher.set_settings(WatcherSettings {
+            mode: WatcherMode::Notify,
+            ..Default::default()
+        });
+        assert!(!watcher.status().polling);
+        while rx.recv_timeout(Duration::from_millis(500)).is_ok() {}
+        std::fs::write(&logpath, b"").unwrap();
+        assert!(touches_log(
+            &rx.recv_timeout(Duration::from_secs(5)).unwrap().unwrap(),
+            &logpath.to_string_lossy()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn polls_without_notifications() {
+        let dir = temp_dir();
+        let logpath = dir.join("Client.txt");
+        let (tx, rx) = channel();
+        let settings = WatcherSettings {
+            mode: WatcherMode::Notify,
+            poll_interval_ms: 10,
+        };
+        let mut watcher = LogWatcher::polling_only(tx, settings, "inotify limit".to_string());
+        watcher.watch(&logpath.to_string_lossy());
+        assert_eq!(watcher.status().state, WatchState::Watching);
+        assert!(watcher.status().polling);
+        assert_eq!(watcher.status().error.as_deref(), Some("inotify limit"));
+
+        std::fs::write(&logpath, b"").unwrap();
+        assert_eq!(next_event(&rx), logpath);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn auto_mode_falls_back_to_polling() {
+        let dir = std::env::temp_dir().join(format!("watcher-{}", uuid::Uuid::new_v4()));
+        let logpath = dir.join("Client.txt");
+        let (tx, rx) = channel();
+        let settings = WatcherSettings {
+            poll_interval_ms: 10,
+            ..Default::default()
+        };
+        let mut watcher = LogWatcher::new(tx, settings).unwrap();
+
+        // directory can't be watched yet
+        watcher.watch(&logpath.to_string_lossy());
+        assert_eq!(watcher.status().state, WatchState::Watching);
+        assert!(watcher.status().polling);
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&logpath, b"").unwrap();
+        assert_eq!(next_event(&rx), logpath);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn silent_notifications_are_noticed() {
+        let notified = AtomicBool::new(false);
+        let mut check = SilenceCheck::default();
+        assert!(!check.missed(false, &notified));
+
+        // reported between the checks
+        assert!(!check.missed(true, &notified));
+        notified.store(true, Ordering::Relaxed);
+        assert!(!check.missed(false, &notified));
+
+        // reported before the change was seen
+        notified.store(true, Ordering::Relaxed);
+        assert!(!check.missed(true, &notified));
+        assert!(!check.missed(false, &notified));
+
+        assert!(!check.missed(true, &notified));
+        assert!(check.missed(false, &notified));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::commands::ChatCommand;
+use crate::model::{TradeInfo, TradeType};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use ts_rs::TS;
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct MacroStep {
+    pub command: ChatCommand,
+    // delay before step is sent
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub delay_ms: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatMacro {
+    pub name: String,
+    pub trade_type: TradeType,
+    pub steps: Vec<MacroStep>,
+}
+
+impl ChatMacro {
+    // macros are written for either incoming or outgoing trades
+    pub fn applies_to(&self, trade: &TradeInfo) -> bool {
+        &self.trade_type == trade.trade_type()
+    }
+
+    pub fn render(&self, trade: &TradeInfo) -> Vec<(Duration, String)> {
+        self.steps
+            .iter()
+            .map(|s| (Duration::from_millis(s.delay_ms), s.command.render(trade)))
+            .collect()
+    }
+}
+
+pub fn default_macros() -> Vec<ChatMacro> {
+    vec![
+        ChatMacro {
+            name: "invite and wait".to_string(),
+            trade_type: TradeType::Incoming,
+            steps: vec![
+                MacroStep {
+                    command: ChatCommand::Invite,
+                    delay_ms: 0,
+                },
+                MacroStep {
+                    command: ChatCommand::Whisper("one sec, finishing map".to_string()),
+                    delay_ms: 300,
+                },
+            ],
+        },
+        ChatMacro {
+            name: "kick and thank".to_string(),
+            trade_type: TradeType::Incoming,
+            steps: vec![
+                MacroStep {
+                    command: ChatCommand::Kick,
+                    delay_ms: 0,
+                },
+                MacroStep {
+                    command: ChatCommand::Whisper("ty, gl".to_string()),
+                    delay_ms: 300,
+                },
+            ],
+        },
+    ]
+}
+
+pub fn for_trade_type<'a>(
+    macros: &'a [ChatMacro],
+    trade_type: &'a TradeType,
+) -> impl Iterator<Item = &'a ChatMacro> {
+    macros.iter().filter(move |m| &m.trade_type == trade_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Model;
+
+    const INCOMING_MSG: &str = r#"2023/10/13 01:54:50 1054470421 cffb0719 [INFO Client 30680] @From SambaLe: Hi, I would like to buy your The Pandemonius, Jade Amulet listed for 4 divine in Ancestor (stash tab "pub"; position: left 11, top 1)"#;
+
+    fn incoming_trade() -> TradeInfo {
+        let mut model = Model::new();
+        model.try_add(INCOMING_MSG).unwrap();
+        let trade = model.ordered_trades(&TradeType::Incoming)[0].clone();
+        trade
+    }
+
+    #[test]
+    fn render_steps() {
+        let trade = incoming_trade();
+        let m = ChatMacro {
+            name: "test".to_string(),
+            trade_type: TradeType::Incoming,
+            steps: vec![
+                MacroStep {
+                    command: ChatCommand::Invite,
+                    delay_ms: 0,
+                },
+                MacroStep {
+                    command: ChatCommand::Whisper("{item} for {price} is yours".to_string()),
+                    delay_ms: 500,
+                },
+                MacroStep {
+                    command: ChatCommand::Raw("/kick {player}".to_string()),
+                    delay_ms: 100,
+                },
+            ],
+        };
+
+        assert!(m.applies_to(&trade));
+        let steps = m.render(&trade);
+        assert_eq!(
+            steps,
+            vec![
+                (Duration::from_millis(0), "/invite SambaLe".to_string()),
+                (
+                    Duration::from_millis(500),
+                    "@SambaLe The Pandemonius, Jade Amulet for 4 divine is yours".to_string()
+                ),
+                (Duration::from_millis(100), "/kick SambaLe".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn outgoing_macro_does_not_apply_to_incoming_trade() {
+        let m = ChatMacro {
+            name: "hideout".to_string(),
+            trade_type: TradeType::Outgoing,
+            steps: vec![],
+        };
+        assert!(!m.applies_to(&incoming_trade()));
+    }
+
+    #[test]
+    fn filter_by_trade_type() {
+        let macros = default_macros();
+        assert_eq!(for_trade_type(&macros, &TradeType::Incoming).count(), 2);
+        assert_eq!(for_trade_type(&macros, &TradeType::Outgoing).count(), 0);
+    }
+}
@@ -0,0 +1,701 @@
+use crate::classifier::{self, FollowUpIntent};
+use crate::history::HandledTrades;
+use crate::parser::{self, Line};
+use chrono::NaiveDateTime;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use ts_rs::TS;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum TradeType {
+    Incoming,
+    Outgoing,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub enum TradeStatus {
+    Active,
+    Declined,
+    Offline,
+    Afk,
+    Failed,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct TradeInfo {
+    id: String,
+    #[serde(rename = "type")]
+    typ: TradeType,
+    item_name: String,
+    player_name: String,
+    time: String,
+    last_message: String,
+    league: String,
+
+    item2_name: Option<String>,
+
+    cost_number: Option<String>,
+    cost_currency: Option<String>,
+
+    stash: Option<String>,
+    left: Option<String>,
+    top: Option<String>,
+
+    status: TradeStatus,
+    status_reason: Option<String>,
+    follow_up: Option<FollowUpIntent>,
+
+    #[serde(skip)]
+    seq: u64,
+    #[serde(skip)]
+    key: String,
+}
+
+impl TradeInfo {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn trade_type(&self) -> &TradeType {
+        &self.typ
+    }
+
+    pub fn player_name(&self) -> &str {
+        &self.player_name
+    }
+
+    pub fn item_name(&self) -> &str {
+        &self.item_name
+    }
+
+    pub fn league(&self) -> &str {
+        &self.league
+    }
+
+    pub fn price(&self) -> String {
+        match (&self.cost_number, &self.cost_currency) {
+            (Some(n), Some(c)) => format!("{} {}", n, c),
+            _ => String::new(),
+        }
+    }
+}
+
+// outgoing trades whispered for the same item, sellers ranked from the best one
+#[derive(Debug, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseIntent {
+    pub item_name: String,
+    pub league: String,
+    pub sellers: Vec<String>,
+    pub suggested: Option<String>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ModelError {
+    #[error("can't parse line: {0}")]
+    ParseError(String),
+    #[error("not a trade line")]
+    NotATradeError,
+    #[error("trade was already closed")]
+    HandledError,
+}
+
+// timestamp and client counter identify a line of Client.txt
+pub fn line_key(line: &str) -> &str {
+    line.split(" [").next().unwrap_or(line)
+}
+
+// game's local time at the start of a line
+pub fn line_time(line: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(line.get(..19)?, "%Y/%m/%d %H:%M:%S").ok()
+}
+
+// seller's autoreply is whispered back as normal message
+fn is_afk_reply(msg: &str) -> bool {
+    let msg = msg.to_lowercase();
+    msg.starts_with("afk") || msg.starts_with("(afk)") || msg.starts_with("autoreply")
+}
+
+pub struct Model {
+    trades: HashMap<String, TradeInfo>,
+    outgoing_callback: Box<dyn Fn(&TradeInfo) + Send>,
+    incoming_callback: Box<dyn Fn(&TradeInfo) + Send>,
+    intent_callback: Box<dyn Fn(&PurchaseIntent) + Send>,
+    closed_callback: Box<dyn Fn(&str) + Send>,
+    next_seq: u64,
+    // system replies are about the latest whisper we sent
+    last_outgoing: Option<String>,
+    handled: HandledTrades,
+}
+
+impl Model {
+    pub fn new() -> Model {
+        Model {
+            trades: HashMap::new(),
+            outgoing_callback: Box::new(|_| {}),
+            incoming_callback: Box::new(|_| {}),
+            intent_callback: Box::new(|_| {}),
+            closed_callback: Box::new(|_| {}),
+            next_seq: 0,
+            last_outgoing: None,
+            handled: HandledTrades::default(),
+        }
+    }
+
+    pub fn set_handled(&mut self, handled: HandledTrades) {
+        self.handled = handled;
+    }
+
+    pub fn outgoing_subscribe<F>(&mut self, cb: F)
+    where
+        F: Fn(&TradeInfo) + Send + 'static,
+    {
+        self.outgoing_callback = Box::new(cb);
+    }
+
+    pub fn incoming_subscribe<F>(&mut self, cb: F)
+    where
+        F: Fn(&TradeInfo) + Send + 'static,
+    {
+        self.incoming_callback = Box::new(cb);
+    }
+
+    pub fn intent_subscribe<F>(&mut self, cb: F)
+    where
+        F: Fn(&PurchaseIntent) + Send + 'static,
+    {
+        self.intent_callback = Box::new(cb);
+    }
+
+    pub fn closed_subscribe<F>(&mut self, cb: F)
+    where
+        F: Fn(&str) + Send + 'static,
+    {
+        self.closed_callback = Box::new(cb);
+    }
+
+    pub fn try_add(&mut self, line: &str) -> Result<(), ModelError> {
+        let whisper = match parser::parse(line) {
+            Line::Whisper(w) => w,
+            Line::SystemReply(status, reason) => {
+                let id = self
+                    .last_outgoing
+                    .clone()
+                    .ok_or(ModelError::NotATradeError)?;
+                return self
+                    .set_status(&id, status, Some(reason.to_string()))
+                    .map(|_| ())
+                    .ok_or(ModelError::NotATradeError);
+            }
+            Line::Other => return Err(ModelError::NotATradeError),
+        };
+        let trade_type = whisper.typ;
+        debug!("char parsed: {}", whisper.player);
+        // a reply is about the latest whisper we sent, even one that isn't about a purchase
+        if trade_type == TradeType::Outgoing {
+            self.last_outgoing = self
+                .trades
+                .values()
+                .find(|t| t.typ == TradeType::Outgoing && t.player_name == whisper.player)
+                .map(|t| t.id.clone());
+        }
+
+        let (trade_info, is_new) = if let Some(v) = self
+            .trades
+            .values_mut()
+            .find(|v| v.player_name == whisper.player)
+        {
+            debug!("old trade info: {}", line);
+            (v, false)
+        } else {
+            let offer = parser::parse_offer(whisper.text)
+                .ok_or_else(|| ModelError::ParseError(line.to_string()))?;
+            if self.handled.contains(line_key(line)) {
+                return Err(ModelError::HandledError);
+            }
+            debug!("parsed line: {}", line);
+            let id = Uuid::new_v4();
+            let localtime = line_time(line)
+                .map(|t| t.time())
+                .unwrap_or_else(|| chrono::Local::now().time());
+            let trade_info = TradeInfo {
+                id: id.to_string(),
+                typ: trade_type.clone(),
+                cost_currency: offer.currency.map(|e| e.to_string()),
+                item_name: offer.item.to_string(),
+                cost_number: offer.cost.map(|e| e.to_string()),
+                last_message: String::new(),
+                player_name: whisper.player.to_string(),
+                time: localtime.format("%H:%M").to_string(),
+                league: offer.league.to_string(),
+                stash: offer.stash.map(|e| e.to_string()),
+                left: offer.left.map(|e| e.to_string()),
+                top: offer.top.map(|e| e.to_string()),
+                item2_name: offer.quality_item.map(|e| e.to_string()),
+                status: TradeStatus::Active,
+                status_reason: None,
+                follow_up: None,
+                seq: self.next_seq,
+                key: line_key(line).to_string(),
+            };
+            self.next_seq += 1;
+            (
+                self.trades.entry(id.to_string()).or_insert(trade_info),
+                true,
+            )
+        };
+        trade_info.last_message = line.to_string();
+        if trade_info.typ == TradeType::Outgoing && trade_type == TradeType::Outgoing {
+            self.last_outgoing = Some(trade_info.id.clone());
+        }
+
+        // follow-up whisper from the other side
+        if !is_new && trade_type == TradeType::Incoming {
+            let msg = whisper.text;
+            if trade_info.typ == TradeType::Outgoing && is_afk_reply(msg) {
+                trade_info.status = TradeStatus::Afk;
+                trade_info.status_reason = Some(msg.to_string());
+            } else if let Some(follow_up) = classifier::classify(msg) {
+                match (&trade_info.typ, &follow_up) {
+                    (TradeType::Incoming, FollowUpIntent::Cancel) => {
+                        let id = trade_info.id.clone();
+                        self.remove_trade(id);
+                        return Ok(());
+                    }
+                    (TradeType::Outgoing, FollowUpIntent::Sold | FollowUpIntent::Cancel) => {
+                        trade_info.status = TradeStatus::Declined;
+                        trade_info.status_reason = Some(msg.to_string());
+                    }
+                    _ => {}
+                }
+                trade_info.follow_up = Some(follow_up);
+            }
+        }
+
+        match trade_info.typ {
+            TradeType::Incoming => (self.incoming_callback)(trade_info),
+            TradeType::Outgoing => (self.outgoing_callback)(trade_info),
+        };
+        if trade_info.typ == TradeType::Outgoing {
+            let id = trade_info.id.clone();
+            self.publish_intent(&id);
+        }
+        Ok(())
+    }
+
+    pub fn get_trade(&self, id: &str) -> Option<&TradeInfo> {
+        self.trades.get(id)
+    }
+
+    // trades of given type from oldest to newest
+    pub fn ordered_trades(&self, typ: &TradeType) -> Vec<&TradeInfo> {
+        let mut trades: Vec<&TradeInfo> = self.trades.values().filter(|t| &t.typ == typ).collect();
+        trades.sort_by_key(|t| t.seq);
+        trades
+    }
+
+    // trade `id` if it is still open, otherwise the oldest one of given type
+    pub fn trade_or_oldest(&self, id: Option<&str>, typ: &TradeType) -> Option<&TradeInfo> {
+        id.and_then(|id| self.trades.get(id))
+            .or_else(|| self.ordered_trades(typ).first().copied())
+    }
+
+    // trade following `id` in arrival order, wraps around to the oldest one
+    pub fn next_trade(&self, id: Option<&str>, typ: &TradeType) -> Option<&TradeInfo> {
+        let trades = self.ordered_trades(typ);
+        let next = id
+            .and_then(|id| trades.iter().position(|t| t.id == id))
+            .map_or(0, |i| i + 1);
+        trades.get(next).or(trades.first()).copied()
+    }
+
+    pub fn remove_trade(&mut self, id: String) {
+        if let Some(trade) = self.trades.remove(&id) {
+            if self.last_outgoing.as_deref() == Some(id.as_str()) {
+                self.last_outgoing = None;
+            }
+            self.handled.insert(trade.key.clone());
+            (self.closed_callback)(&id);
+            if trade.typ == TradeType::Outgoing {
+                let intent = self.intent(&trade.item_name, &trade.league);
+                (self.intent_callback)(&intent);
+            }
+        }
+    }
+
+    fn intent(&self, item_name: &str, league: &str) -> PurchaseIntent {
+        let mut sellers: Vec<&TradeInfo> = self
+            .trades
+            .values()
+            .filter(|t| t.typ == TradeType::Outgoing)
+            .filter(|t| t.item_name == item_name && t.league == league)
+            .collect();
+        // prices in different currencies aren't converted, usually sellers list item
+        // in the same currency anyway
+        let price = |t: &TradeInfo| -> f64 {
+            t.cost_number
+                .as_deref()
+                .and_then(|c| c.parse().ok())
+                .unwrap_or(f64::INFINITY)
+        };
+        sellers.sort_by(|a, b| {
+            price(a)
+                .partial_cmp(&price(b))
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.seq.cmp(&b.seq))
+        });
+
+        PurchaseIntent {
+            item_name: item_name.to_string(),
+            league: league.to_string(),
+            suggested: sellers
+                .iter()
+                .find(|t| t.status == TradeStatus::Active)
+                .map(|t| t.id.clone()),
+            sellers: sellers.iter().map(|t| t.id.clone()).collect(),
+        }
+    }
+
+    pub fn intent_for(&self, id: &str) -> Option<PurchaseIntent> {
+        let trade = self.trades.get(id)?;
+        if trade.typ != TradeType::Outgoing {
+            return None;
+        }
+        Some(self.intent(&trade.item_name, &trade.league))
+    }
+
+    // intents of all items being bought, oldest first
+    pub fn intents(&self) -> Vec<PurchaseIntent> {
+        let mut items: Vec<(&str, &str)> = vec![];
+        for t in self.ordered_trades(&TradeType::Outgoing) {
+            if !items.contains(&(t.item_name.as_str(), t.league.as_str())) {
+                items.push((&t.item_name, &t.league));
+            }
+        }
+        items
+            .into_iter()
+            .map(|(item, league)| self.intent(item, league))
+            .collect()
+    }
+
+    fn publish_intent(&self, id: &str) {
+        if let Some(intent) = self.intent_for(id) {
+            (self.intent_callback)(&intent);
+        }
+    }
+
+    // item bought from this seller, the other sellers of the same item aren't needed anymore
+    pub fn complete_trade(&mut self, id: &str) -> Vec<String> {
+        let others: Vec<String> = match self.intent_for(id) {
+            Some(intent) => intent.sellers.into_iter().filter(|s| s != id).collect(),
+            None => vec![],
+        };
+        for other in others.iter() {
+            self.remove_trade(other.clone());
+        }
+        self.remove_trade(id.to_string());
+        others
+    }
+
+    // seller won't sell, next best seller becomes suggested one
+    pub fn decline_trade(&mut self, id: &str) -> Option<PurchaseIntent> {
+        self.set_status(id, TradeStatus::Declined, None)
+    }
+
+    fn set_status(
+        &mut self,
+        id: &str,
+        status: TradeStatus,
+        reason: Option<String>,
+    ) -> Option<PurchaseIntent> {
+        let trade = self.trades.get_mut(id)?;
+        trade.status = status;
+        trade.status_reason = reason;
+        match trade.typ {
+            TradeType::Incoming => (self.incoming_callback)(trade),
+            TradeType::Outgoing => (self.outgoing_callback)(trade),
+        };
+        let intent = self.intent_for(id);
+        if let Some(intent) = intent.as_ref() {
+            (self.intent_callback)(intent);
+        }
+        intent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utilities::Callable;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn messages() {
+        let mut model = Model::new();
+        let clb = Callable::new();
+        {
+            let clb2 = clb.clone();
+            model.outgoing_subscribe(move |_og| {
+                clb2.call();
+            });
+            let clb = clb.clone();
+            model.incoming_subscribe(move |_og| {
+                clb.call();
+            });
+        }
+
+        let msgs = [
+            r#"@From <TestGuild> 匚丹匚丹几丹: Hi, I would like to buy your Aegis Aurora Champion Kite Shield listed for 5 awakened-sextant in Ancestor (stash tab "~b/o 4.99 awakened-sextant"; position: left 9, top 7)"#,
+            r#"@To 匚丹匚丹几丹: Hi, I would like to buy your Aegis Aurora Champion Kite Shield listed for 5 awakened-sextant in Ancestor (stash tab "~b/o 4.99 awakened-sextant"; position: left 9, top 7)"#,
+            r#"16642: 2020/06/23 17:07:09 1067255656 b5c [INFO Client 10768] @From sethmera: Hi, I would like to buy your Onslaught Bind Chain Belt listed for 1 awakened-sextant in Harvest (stash tab "~price 1 chaos"; position: left 2, top 1)"#,
+            r#"2023/10/13 01:54:50 1054470421 cffb0719 [INFO Client 30680] @From SambaLe: Hi, I would like to buy your The Pandemonius, Jade Amulet listed for 4 divine in Ancestor (stash tab "pub"; position: left 11, top 1)"#,
+        ];
+
+        for m in msgs.iter() {
+            model.try_add(m).unwrap();
+        }
+        assert_eq!(clb.count(), msgs.len() as u64);
+    }
+
+    #[test]
+    fn trades_order() {
+        let mut model = Model::new();
+        let msgs = [
+            r#"@From first: Hi, I would like to buy your Onslaught Bind Chain Belt listed for 1 awakened-sextant in Harvest (stash tab "~price 1 chaos"; position: left 2, top 1)"#,
+            r#"@To seller: Hi, I would like to buy your Onslaught Bind Chain Belt listed for 1 awakened-sextant in Harvest (stash tab "~price 1 chaos"; position: left 2, top 1)"#,
+            r#"@From second: Hi, I would like to buy your Onslaught Bind Chain Belt listed for 1 awakened-sextant in Harvest (stash tab "~price 1 chaos"; position: left 2, top 1)"#,
+            r#"@From third: Hi, I would like to buy your Onslaught Bind Chain Belt listed for 1 awakened-sextant in Harvest (stash tab "~price 1 chaos"; position: left 2, top 1)"#,
+        ];
+        for m in msgs.iter() {
+            model.try_add(m).unwrap();
+        }
+
+        let names = |v: Vec<&TradeInfo>| -> Vec<String> {
+            v.iter().map(|t| t.player_name.clone()).collect()
+        };
+        assert_eq!(
+            names(model.ordered_trades(&TradeType::Incoming)),
+            vec!["first", "second", "third"]
+        );
+
+        let first = model.next_trade(None, &TradeType::Incoming).unwrap();
+        assert_eq!(first.player_name, "first");
+        let second = model
+            .next_trade(Some(&first.id), &TradeType::Incoming)
+            .unwrap();
+        assert_eq!(second.player_name, "second");
+        let third = model
+            .next_trade(Some(&second.id), &TradeType::Incoming)
+            .unwrap();
+        let wrapped = model
+            .next_trade(Some(&third.id), &TradeType::Incoming)
+            .unwrap();
+        assert_eq!(wrapped.player_name, "first");
+        assert!(model.next_trade(None, &TradeType::Outgoing).is_some());
+    }
+
+    #[test]
+    fn purchase_intents() {
+        let mut model = Model::new();
+        let closed = Arc::new(Mutex::new(vec![]));
+        {
+            let closed = Arc::clone(&closed);
+            model.closed_subscribe(move |id| closed.lock().unwrap().push(id.to_string()));
+        }
+        let msgs = [
+            r#"@To expensive: Hi, I would like to buy your Tabula Rasa Simple Robe listed for 12 chaos in Ancestor (stash tab "~price 12 chaos"; position: left 1, top 1)"#,
+            r#"@To cheap: Hi, I would like to buy your Tabula Rasa Simple Robe listed for 8 chaos in Ancestor (stash tab "~price 8 chaos"; position: left 1, top 1)"#,
+            r#"@To cheaplater: Hi, I would like to buy your Tabula Rasa Simple Robe listed for 8 chaos in Ancestor (stash tab "~price 8 chaos"; position: left 1, top 1)"#,
+            r#"@To other: Hi, I would like to buy your Goldrim Leather Cap listed for 1 chaos in Ancestor (stash tab "~price 1 chaos"; position: left 1, top 1)"#,
+        ];
+        for m in msgs.iter() {
+            model.try_add(m).unwrap();
+        }
+
+        let id_of = |model: &Model, name: &str| -> String {
+            model
+                .trades
+                .values()
+                .find(|t| t.player_name == name)
+                .unwrap()
+                .id
+                .clone()
+        };
+        let cheap = id_of(&model, "cheap");
+        let cheaplater = id_of(&model, "cheaplater");
+        let expensive = id_of(&model, "expensive");
+        let other = id_of(&model, "other");
+
+        let intent = model.intent_for(&expensive).unwrap();
+        assert_eq!(intent.item_name, "Tabula Rasa Simple Robe");
+        assert_eq!(
+            intent.sellers,
+            vec![cheap.clone(), cheaplater.clone(), expensive.clone()]
+        );
+        assert_eq!(intent.suggested, Some(cheap.clone()));
+
+        let intent = model.decline_trade(&cheap).unwrap();
+        assert_eq!(intent.suggested, Some(cheaplater.clone()));
+
+        let mut others = model.complete_trade(&cheaplater);
+        others.sort();
+        let mut expected = vec![cheap.clone(), expensive.clone()];
+        expected.sort();
+        assert_eq!(others, expected);
+        assert_eq!(closed.lock().unwrap().len(), 3);
+        assert!(model.get_trade(&other).is_some());
+        assert_eq!(model.ordered_trades(&TradeType::Outgoing).len(), 1);
+    }
+
+    #[test]
+    fn system_replies() {
+        let mut model = Model::new();
+        let whisper = |name: &str| {
+            format!(
+                r#"2023/10/13 01:54:50 1054470421 cffb0719 [INFO Client 30680] @To {}: Hi, I would like to buy your Tabula Rasa Simple Robe listed for 8 chaos in Ancestor (stash tab "~price 8 chaos"; position: left 1, top 1)"#,
+                name
+            )
+        };
+        let status_of = |model: &Model, name: &str| -> (TradeStatus, Option<String>) {
+            let t = model
+                .trades
+                .values()
+                .find(|t| t.player_name == name)
+                .unwrap();
+            (t.status.clone(), t.status_reason.clone())
+        };
+
+        assert!(matches!(
+            model.try_add("2023/10/13 01:54:51 1054470421 cffb0719 [INFO Client 30680] : That character is not online."),
+            Err(ModelError::NotATradeError)
+        ));
+
+        model.try_add(&whisper("first")).unwrap();
+        model.try_add(&whisper("second")).unwrap();
+        model
+            .try_add("2023/10/13 01:54:51 1054470421 cffb0719 [INFO Client 30680] : That character is not online.")
+            .unwrap();
+        assert_eq!(status_of(&model, "first").0, TradeStatus::Active);
+        assert_eq!(
+            status_of(&model, "second"),
+            (
+                TradeStatus::Offline,
+                Some("That character is not online.".to_string())
+            )
+        );
+        let first = model
+            .trades
+            .values()
+            .find(|t| t.player_name == "first")
+            .unwrap();
+        assert_eq!(
+            model.intent_for(&first.id.clone()).unwrap().suggested,
+            Some(first.id.clone())
+        );
+
+        model.try_add(&whisper("third")).unwrap();
+        model
+            .try_add(r#"2023/10/13 01:54:52 1054470421 cffb0719 [INFO Client 30680] @From third: AFK: back in 10 min"#)
+            .unwrap();
+        assert_eq!(
+            status_of(&model, "third"),
+            (TradeStatus::Afk, Some("AFK: back in 10 min".to_string()))
+        );
+
+        model.try_add(&whisper("first")).unwrap();
+        model
+            .try_add(r#": AFK mode is now ON. Autoreply "brb""#)
+            .unwrap();
+        assert_eq!(status_of(&model, "first").0, TradeStatus::Afk);
+
+        assert!(model.try_add(": Trade accepted.").is_err());
+    }
+
+    #[test]
+    fn system_reply_after_other_whispers() {
+        let mut model = Model::new();
+        let offline = "2023/10/13 01:54:51 1054470421 cffb0719 [INFO Client 30680] : That character is not online.";
+        let status_of = |model: &Model, name: &str| {
+            model
+                .trades
+                .values()
+                .find(|t| t.player_name == name)
+                .map(|t| t.status.clone())
+        };
+        model.try_add(r#"@To seller: Hi, I would like to buy your Tabula Rasa Simple Robe listed for 8 chaos in Ancestor (stash tab "~price 8 chaos"; position: left 1, top 1)"#).unwrap();
+        model.try_add(r#"@From buyer: Hi, I would like to buy your Goldrim Leather Cap listed for 1 chaos in Ancestor (stash tab "~price 1 chaos"; position: left 1, top 1)"#).unwrap();
+
+        // whisper that isn't a trade
+        assert!(model.try_add("@To friend: hi, party?").is_err());
+        assert!(model.try_add(offline).is_err());
+        // whisper to a buyer
+        model.try_add("@To buyer: sure, invite sent").unwrap();
+        assert!(model.try_add(offline).is_err());
+        assert_eq!(status_of(&model, "seller"), Some(TradeStatus::Active));
+
+        // closed trade
+        model.try_add("@To seller: still selling?").unwrap();
+        let id = model.ordered_trades(&TradeType::Outgoing)[0].id.clone();
+        model.remove_trade(id);
+        assert!(model.try_add(offline).is_err());
+    }
+
+    #[test]
+    fn follow_ups() {
+        let mut model = Model::new();
+        let buyer = r#"@From buyer: Hi, I would like to buy your Tabula Rasa Simple Robe listed for 8 chaos in Ancestor (stash tab "~price 8 chaos"; position: left 1, top 1)"#;
+        let seller = r#"@To seller: Hi, I would like to buy your Goldrim Leather Cap listed for 1 chaos in Ancestor (stash tab "~price 1 chaos"; position: left 1, top 1)"#;
+        model.try_add(buyer).unwrap();
+        model.try_add(seller).unwrap();
+        let find = |model: &Model, name: &str| -> Option<TradeInfo> {
+            model
+                .trades
+                .values()
+                .find(|t| t.player_name == name)
+                .cloned()
+        };
+
+        model.try_add("@From buyer: can you do 6c?").unwrap();
+        assert_eq!(
+            find(&model, "buyer").unwrap().follow_up,
+            Some(FollowUpIntent::CounterOffer {
+                cost_number: Some("6".to_string()),
+                cost_currency: Some("c".to_string())
+            })
+        );
+        // our own messages aren't classified
+        model.try_add("@To buyer: nvm").unwrap();
+        assert!(find(&model, "buyer").is_some());
+
+        model.try_add("@From seller: sold sorry").unwrap();
+        let s = find(&model, "seller").unwrap();
+        assert_eq!(s.follow_up, Some(FollowUpIntent::Sold));
+        assert_eq!(s.status, TradeStatus::Declined);
+
+        model.try_add("@From buyer: nvm found another").unwrap();
+        assert!(find(&model, "buyer").is_none());
+    }
+
+    #[test]
+    fn closed_trades_not_restored() {
+        let mut model = Model::new();
+        let line = r#"2023/10/13 01:54:50 1054470421 cffb0719 [INFO Client 30680] @From buyer: Hi, I would like to buy your Tabula Rasa Simple Robe listed for 8 chaos in Ancestor (stash tab "~price 8 chaos"; position: left 1, top 1)"#;
+        model.try_add(line).unwrap();
+        let trade = model.ordered_trades(&TradeType::Incoming)[0].clone();
+        assert_eq!(trade.time, "01:54");
+        model.remove_trade(trade.id.clone());
+
+        // replayed line of closed trade
+        assert!(matches!(model.try_add(line), Err(ModelError::HandledError)));
+        // same whisper sent again later
+        model
+            .try_add(&line.replace("01:54:50 1054470421", "01:58:10 1054670421"))
+            .unwrap();
+        assert_eq!(model.ordered_trades(&TradeType::Incoming).len(), 1);
+    }
+}
@@ -0,0 +1,226 @@
+use crate::file_line_reader::LineSink;
+use crate::model::{Model, PurchaseIntent, TradeInfo, TradeType};
+use log::{debug, error};
+use serde::Serialize;
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{channel, Sender},
+        Arc,
+    },
+};
+use ts_rs::TS;
+
+type Job = Box<dyn FnOnce(&mut Model) + Send>;
+
+// what subscribers of the model are told, in the order it happened
+#[derive(Debug, Clone)]
+pub enum ModelEvent {
+    NewIncoming(TradeInfo),
+    NewOutgoing(TradeInfo),
+    IntentUpdated(PurchaseIntent),
+    Closed(String),
+}
+
+// payload of an event with the model version it brought, versions of consecutive
+// events differ by one so a gap means an event was missed
+#[derive(Debug, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct Versioned<T> {
+    #[ts(type = "number")]
+    pub version: u64,
+    pub data: T,
+}
+
+// everything the model holds, as of `version`
+#[derive(Debug, Serialize, Clone, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct TradesSnapshot {
+    #[ts(type = "number")]
+    pub version: u64,
+    pub incoming: Vec<TradeInfo>,
+    pub outgoing: Vec<TradeInfo>,
+    pub intents: Vec<PurchaseIntent>,
+}
+
+// model owned by a thread of its own, everything else sends it jobs; events are
+// published from another thread so a slow subscriber doesn't hold up the model and
+// a subscriber can call back into it
+#[derive(Clone)]
+pub struct ModelHandle {
+    jobs: Sender<Job>,
+    // only changed on the model thread, as events are raised
+    version: Arc<AtomicU64>,
+}
+
+impl ModelHandle {
+    pub fn spawn<P>(mut model: Model, publish: P) -> Self
+    where
+        P: Fn(Versioned<ModelEvent>) + Send + 'static,
+    {
+        let version = Arc::new(AtomicU64::new(0));
+        let (etx, erx) = channel();
+        {
+            let raise = |tx: Sender<Versioned<ModelEvent>>, version: Arc<AtomicU64>| {
+                move |data: ModelEvent| {
+                    let version = version.fetch_add(1, Ordering::SeqCst) + 1;
+                    let _ = tx.send(Versioned { version, data });
+                }
+            };
+            let raised = raise(etx.clone(), Arc::clone(&version));
+            model.incoming_subscribe(move |t| raised(ModelEvent::NewIncoming(t.clone())));
+            let raised = raise(etx.clone(), Arc::clone(&version));
+            model.outgoing_subscribe(move |t| raised(ModelEvent::NewOutgoing(t.clone())));
+            let raised = raise(etx.clone(), Arc::clone(&version));
+            model.intent_subscribe(move |i| raised(ModelEvent::IntentUpdated(i.clone())));
+            let raised = raise(etx, Arc::clone(&version));
+            model.closed_subscribe(move |id| raised(ModelEvent::Closed(id.to_string())));
+        }
+        std::thread::Builder::new()
+            .name("model-events".to_string())
+            .spawn(move || {
+                for ev in erx {
+                    if catch_unwind(AssertUnwindSafe(|| publish(ev))).is_err() {
+                        error!("model event subscriber panicked");
+                    }
+                }
+            })
+            .expect("can't start model event publisher");
+
+        let (jtx, jrx) = channel::<Job>();
+        std::thread::Builder::new()
+            .name("model".to_string())
+            .spawn(move || {
+                for job in jrx {
+                    if catch_unwind(AssertUnwindSafe(|| job(&mut model))).is_err() {
+                        error!("model job panicked");
+                    }
+                }
+                debug!("model stopped");
+            })
+            .expect("can't start model");
+
+        ModelHandle { jobs: jtx, version }
+    }
+
+    // runs `f` on the model without waiting for it
+    pub fn send<F: FnOnce(&mut Model) + Send + 'static>(&self, f: F) {
+        if self.jobs.send(Box::new(f)).is_err() {
+            error!("model is not running");
+        }
+    }
+
+    // runs `f` on the model and waits for the result, None if it panicked;
+    // must not be called from inside of a job
+    pub fn call<R, F>(&self, f: F) -> Option<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Model) -> R + Send + 'static,
+    {
+        let (tx, rx) = channel();
+        self.send(move |m| {
+            let _ = tx.send(f(m));
+        });
+        rx.recv().ok()
+    }
+
+    // taken between events, so it is consistent with the version it reports
+    pub fn snapshot(&self) -> Option<TradesSnapshot> {
+        let version = Arc::clone(&self.version);
+        self.call(move |m| {
+            let trades =
+                |typ| -> Vec<TradeInfo> { m.ordered_trades(&typ).into_iter().cloned().collect() };
+            TradesSnapshot {
+                version: version.load(Ordering::SeqCst),
+                incoming: trades(TradeType::Incoming),
+                outgoing: trades(TradeType::Outgoing),
+                intents: m.intents(),
+            }
+        })
+    }
+}
+
+impl LineSink for ModelHandle {
+    fn add_lines(&mut self, lines: Vec<String>) {
+        self.send(move |m| {
+            for l in lines.iter() {
+                let r = m.try_add(l);
+                debug!("result processing line: {} {:?}", l, r);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::Mutex, time::Duration};
+
+    const INCOMING: &str = r#"2023/10/13 01:54:50 1054470421 cffb0719 [INFO Client 30680] @From SambaLe: Hi, I would like to buy your The Pandemonius, Jade Amulet listed for 4 divine in Ancestor (stash tab "pub"; position: left 11, top 1)"#;
+
+    #[test]
+    fn events_are_published_off_the_model_thread() {
+        let (tx, rx) = channel();
+        let slot: Arc<Mutex<Option<ModelHandle>>> = Arc::new(Mutex::new(None));
+        let handle = {
+            let slot = Arc::clone(&slot);
+            ModelHandle::spawn(Model::new(), move |ev| {
+                // subscriber looking at the model doesn't deadlock it
+                let model = slot.lock().unwrap().clone().unwrap();
+                let open = model
+                    .call(|m| m.ordered_trades(&TradeType::Incoming).len())
+                    .unwrap();
+                let name = match ev.data {
+                    ModelEvent::NewIncoming(t) => format!("incoming {}", t.player_name()),
+                    ModelEvent::Closed(_) => "closed".to_string(),
+                    other => format!("{:?}", other),
+                };
+                tx.send((name, open)).unwrap();
+            })
+        };
+        *slot.lock().unwrap() = Some(handle.clone());
+
+        let mut sink = handle.clone();
+        sink.add_lines(vec!["not a trade".to_string(), INCOMING.to_string()]);
+        let id = handle
+            .call(|m| m.ordered_trades(&TradeType::Incoming)[0].id().to_string())
+            .unwrap();
+        handle.send(move |m| m.remove_trade(id));
+
+        let next = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(next().0, "incoming SambaLe");
+        assert_eq!(next(), ("closed".to_string(), 0));
+    }
+
+    #[test]
+    fn snapshot_matches_event_versions() {
+        const OUTGOING: &str = r#"2023/10/13 01:55:00 1054480421 cffb0719 [INFO Client 30680] @To seller: Hi, I would like to buy your Goldrim Leather Cap listed for 1 chaos in Ancestor (stash tab "~price 1 chaos"; position: left 1, top 1)"#;
+        let (tx, rx) = channel();
+        let handle = ModelHandle::spawn(Model::new(), move |ev| tx.send(ev.version).unwrap());
+        assert_eq!(handle.snapshot().unwrap().version, 0);
+
+        let mut sink = handle.clone();
+        sink.add_lines(vec![INCOMING.to_string(), OUTGOING.to_string()]);
+        let snapshot = handle.snapshot().unwrap();
+        // incoming trade, then outgoing trade and its intent
+        let versions: Vec<u64> = rx.iter().take(3).collect();
+        assert_eq!(versions, vec![1, 2, 3]);
+        assert_eq!(snapshot.version, 3);
+        assert_eq!(snapshot.incoming[0].player_name(), "SambaLe");
+        assert_eq!(snapshot.outgoing[0].player_name(), "seller");
+        assert_eq!(snapshot.intents[0].item_name, "Goldrim Leather Cap");
+    }
+
+    #[test]
+    fn panicking_job_keeps_model_running() {
+        let handle = ModelHandle::spawn(Model::new(), |_| {});
+        assert_eq!(handle.call(|_| -> u32 { panic!("broken job") }), None);
+        let mut sink = handle.clone();
+        sink.add_lines(vec![INCOMING.to_string()]);
+        assert_eq!(
+            handle.call(|m| m.ordered_trades(&TradeType::Incoming).len()),
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use crate::model_actor::ModelEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use ts_rs::TS;
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlaySettings {
+    #[serde(default = "default_true")]
+    pub always_on_top: bool,
+    // mouse goes through overlays to the game, trades are handled with hotkeys then
+    #[serde(default)]
+    pub click_through: bool,
+    // overlays are created unfocused and never focused when shown
+    #[serde(default = "default_true")]
+    pub prevent_focus: bool,
+    // overlays stay hidden, trades are still tracked and shown once it is turned off
+    #[serde(default)]
+    pub do_not_disturb: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        OverlaySettings {
+            always_on_top: true,
+            click_through: false,
+            prevent_focus: true,
+            do_not_disturb: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverlayWindow {
+    Incoming,
+    Outgoing,
+}
+
+impl OverlayWindow {
+    pub const ALL: [OverlayWindow; 2] = [OverlayWindow::Incoming, OverlayWindow::Outgoing];
+
+    // label of the window in tauri.conf.json
+    pub fn label(&self) -> &'static str {
+        match self {
+            OverlayWindow::Incoming => "incoming",
+            OverlayWindow::Outgoing => "outgoing",
+        }
+    }
+}
+
+type WindowCallback = Box<dyn Fn(OverlayWindow, bool, &OverlaySettings) + Send>;
+
+// decides when overlay windows are shown from the trades the model publishes:
+// a window is shown with its first open trade and hidden once none remain
+pub struct Overlay {
+    settings: OverlaySettings,
+    incoming: HashSet<String>,
+    outgoing: HashSet<String>,
+    shown: HashSet<OverlayWindow>,
+    window_callback: WindowCallback,
+}
+
+impl Overlay {
+    pub fn new(settings: OverlaySettings) -> Self {
+        Overlay {
+            settings,
+            incoming: HashSet::new(),
+            outgoing: HashSet::new(),
+            shown: HashSet::new(),
+            window_callback: Box::new(|_, _, _| {}),
+        }
+    }
+
+    // called with the wanted visibility of a window whenever it or the settings change
+    pub fn window_subscribe<F>(&mut self, cb: F)
+    where
+        F: Fn(OverlayWindow, bool, &OverlaySettings) + Send + 'static,
+    {
+        self.window_callback = Box::new(cb);
+    }
+
+    pub fn settings(&self) -> &OverlaySettings {
+        &self.settings
+    }
+
+    pub fn handle(&mut self, ev: &ModelEvent) {
+        match ev {
+            ModelEvent::NewIncoming(t) => {
+                self.incoming.insert(t.id().to_string());
+            }
+            ModelEvent::NewOutgoing(t) => {
+                self.outgoing.insert(t.id().to_string());
+            }
+            ModelEvent::Closed(id) => {
+                self.incoming.remove(id);
+                self.outgoing.remove(id);
+            }
+            ModelEvent::IntentUpdated(_) => return,
+        }
+        self.update(false);
+    }
+
+    // visible windows are updated too, they may have to drop always on top or click-through
+    pub fn set_settings(&mut self, settings: OverlaySettings) {
+        self.settings = settings;
+        self.update(true);
+    }
+
+    pub fn is_shown(&self, window: OverlayWindow) -> bool {
+        self.shown.contains(&window)
+    }
+
+    fn wanted(&self, window: OverlayWindow) -> bool {
+        let trades = match window {
+            OverlayWindow::Incoming => &self.incoming,
+            OverlayWindow::Outgoing => &self.outgoing,
+        };
+        !self.settings.do_not_disturb && !trades.is_empty()
+    }
+
+    fn update(&mut self, settings_changed: bool) {
+        for window in OverlayWindow::ALL.iter() {
+            let wanted = self.wanted(*window);
+            // windows are shown only once, showing again would bring them to the front
+            if wanted == self.is_shown(*window) && !(settings_changed && wanted) {
+                continue;
+            }
+            if wanted {
+                self.shown.insert(*window);
+            } else {
+                self.shown.remove(window);
+            }
+            (self.window_callback)(*window, wanted, &self.settings);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Model;
+    use crate::model_actor::ModelHandle;
+    use std::sync::{
+        mpsc::{channel, Receiver},
+        Arc, Mutex,
+    };
+    use std::time::Duration;
+
+    const INCOMING: &str = r#"2023/10/13 01:54:50 1054470421 cffb0719 [INFO Client 30680] @From buyer: Hi, I would like to buy your Tabula Rasa Simple Robe listed for 8 chaos in Ancestor (stash tab "~price 8 chaos"; position: left 1, top 1)"#;
+    const SECOND: &str = r#"2023/10/13 01:54:52 1054472421 cffb0719 [INFO Client 30680] @From other: Hi, I would like to buy your Goldrim Leather Cap listed for 1 chaos in Ancestor (stash tab "~price 1 chaos"; position: left 2, top 1)"#;
+
+    // model feeding an overlay that records what it asks the windows to do
+    type Shown = Receiver<(&'static str, bool)>;
+
+    fn overlay() -> (ModelHandle, Arc<Mutex<Overlay>>, Shown) {
+        let (tx, rx) = channel();
+        let mut overlay = Overlay::new(OverlaySettings::default());
+        overlay.window_subscribe(move |w, shown, _| tx.send((w.label(), shown)).unwrap());
+        let overlay = Arc::new(Mutex::new(overlay));
+        let o = Arc::clone(&overlay);
+        let model = ModelHandle::spawn(Model::new(), move |ev| o.lock().unwrap().handle(&ev.data));
+        (model, overlay, rx)
+    }
+
+    fn add(model: &ModelHandle, line: &str) {
+        let line = line.to_string();
+        model.call(move |m| m.try_add(&line).unwrap());
+    }
+
+    fn close(model: &ModelHandle, player: &'static str) {
+        model.call(move |m| {
+            let id = m
+                .ordered_trades(&crate::model::TradeType::Incoming)
+                .into_iter()
+                .find(|t| t.player_name() == player)
+                .map(|t| t.id().to_string())
+                .unwrap();
+            m.remove_trade(id);
+        });
+    }
+
+    #[test]
+    fn shown_with_first_trade_and_hidden_after_last() {
+        let (model, _overlay, rx) = overlay();
+        let next = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        add(&model, INCOMING);
+        assert_eq!(next(), ("incoming", true));
+        // second trade and its update don't show the window again
+        add(&model, SECOND);
+        add(&model, SECOND);
+        close(&model, "buyer");
+        close(&model, "other");
+        assert_eq!(next(), ("incoming", false));
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn do_not_disturb_holds_windows_back() {
+        let (model, overlay, rx) = overlay();
+        let next = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        let settings = |dnd: bool| OverlaySettings {
+            do_not_disturb: dnd,
+            ..Default::default()
+        };
+
+        overlay.lock().unwrap().set_settings(settings(true));
+        add(&model, INCOMING);
+        // model publishes on its own thread, wait until the overlay had the event
+        model.call(|_| ());
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!overlay.lock().unwrap().is_shown(OverlayWindow::Incoming));
+        assert!(rx.try_recv().is_err());
+
+        overlay.lock().unwrap().set_settings(settings(false));
+        assert_eq!(next(), ("incoming", true));
+        // visible window gets the new settings as well
+        overlay.lock().unwrap().set_settings(OverlaySettings {
+            click_through: true,
+            ..Default::default()
+        });
+        assert_eq!(next(), ("incoming", true));
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,214 @@
+use crate::model::{TradeStatus, TradeType};
+use memchr::{memchr_iter, memmem};
+use once_cell::sync::Lazy;
+use regex::{Regex, RegexSet};
+
+// Client.txt line as far as trades are concerned, borrowing from the line so nothing
+// is allocated until a trade is actually created
+#[derive(Debug, PartialEq)]
+pub enum Line<'a> {
+    Whisper(Whisper<'a>),
+    // game's own reply in chat, about the latest whisper we sent
+    SystemReply(TradeStatus, &'a str),
+    Other,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Whisper<'a> {
+    pub typ: TradeType,
+    pub guild: Option<&'a str>,
+    pub player: &'a str,
+    // text after `Name:`
+    pub text: &'a str,
+}
+
+// trade offer of a whisper made from one of the english templates
+#[derive(Debug, PartialEq)]
+pub struct Offer<'a> {
+    pub item: &'a str,
+    pub cost: Option<&'a str>,
+    pub currency: Option<&'a str>,
+    pub league: &'a str,
+    pub stash: Option<&'a str>,
+    pub left: Option<&'a str>,
+    pub top: Option<&'a str>,
+    pub quality_item: Option<&'a str>,
+}
+
+static WHISPER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"@(?<type>(?:From|To)) (?<guild>(?:<.+>){0,1})\s*(?<char>\w+):"#).unwrap()
+});
+
+// templates from the most specific one, first that matches wins; offers from the trade site
+// end with where the item is in the seller's stash
+const OFFER_TEMPLATES: &[&str] = &[
+    r#"Hi, I would like to buy your (?<item>[\w\s,]+) listed for (?<cost>[\d\.]+) (?<currency>[\w-]+) in (?<league>\w+)(?: \(stash tab "(?<stash>.*?)"; position: left (?<left>\d+), top (?<top>\d+)\))?"#,
+    r#"Hi, I would like to buy your (?<item>[\w\s,]+) in (?<league>\w+)(?: \(stash tab "(?<stash>.*?)"; position: left (?<left>\d+), top (?<top>\d+)\))?"#,
+    r#"Hi, I'd like to buy your (?<item>[\w\s,]+) for my (?<item2>[\w\s]+) in (?<league>\w+)"#,
+];
+
+// all templates are tried in one pass, captures are taken only from the one that matched
+static OFFER_SET: Lazy<RegexSet> = Lazy::new(|| RegexSet::new(OFFER_TEMPLATES).unwrap());
+static OFFERS: Lazy<Vec<Regex>> = Lazy::new(|| {
+    OFFER_TEMPLATES
+        .iter()
+        .map(|t| Regex::new(t).unwrap())
+        .collect()
+});
+
+static QUALITY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"level (?<lvl>\d+) (?<quality>\d+)% (?<item>.*)"#).unwrap());
+
+static SYSTEM_REPLIES: &[(&str, TradeStatus)] = &[
+    ("That character is not online.", TradeStatus::Offline),
+    ("AFK mode is now ON", TradeStatus::Afk),
+    ("You cannot whisper", TradeStatus::Failed),
+    ("Your message was not sent", TradeStatus::Failed),
+];
+
+static SYSTEM_PREFIX: Lazy<memmem::Finder> = Lazy::new(|| memmem::Finder::new(b"] : "));
+
+// whispers always contain `@From ` or `@To `, everything else is rejected without a regex
+fn may_be_whisper(line: &[u8]) -> bool {
+    memchr_iter(b'@', line).any(|i| {
+        let rest = &line[i + 1..];
+        rest.starts_with(b"From ") || rest.starts_with(b"To ")
+    })
+}
+
+// system messages have no sender, so the text follows `] : ` or starts the line
+fn system_message(line: &str) -> Option<&str> {
+    if let Some(msg) = line.strip_prefix(": ") {
+        return Some(msg);
+    }
+    SYSTEM_PREFIX
+        .find(line.as_bytes())
+        .map(|i| &line[i + "] : ".len()..])
+}
+
+fn system_reply(line: &str) -> Option<(TradeStatus, &str)> {
+    let msg = system_message(line)?.trim();
+    SYSTEM_REPLIES
+        .iter()
+        .find(|(prefix, _)| msg.starts_with(prefix))
+        .map(|(_, status)| (status.clone(), msg))
+}
+
+fn whisper(line: &str) -> Option<Whisper<'_>> {
+    if !may_be_whisper(line.as_bytes()) {
+        return None;
+    }
+    let c = WHISPER.captures(line)?;
+    let typ = match &c["type"] {
+        "From" => TradeType::Incoming,
+        _ => TradeType::Outgoing,
+    };
+    let guild = c
+        .name("guild")
+        .map(|g| g.as_str().trim_matches(|c| c == '<' || c == '>'));
+    Some(Whisper {
+        typ,
+        guild,
+        player: c.name("char")?.as_str(),
+        text: line[c.get(0)?.end()..].trim(),
+    })
+}
+
+pub fn parse(line: &str) -> Line<'_> {
+    if let Some(w) = whisper(line) {
+        return Line::Whisper(w);
+    }
+    match system_reply(line) {
+        Some((status, msg)) => Line::SystemReply(status, msg),
+        None => Line::Other,
+    }
+}
+
+pub fn parse_offer(text: &str) -> Option<Offer<'_>> {
+    let template = OFFER_SET.matches(text).into_iter().next()?;
+    let c = OFFERS[template].captures(text)?;
+    let group = |name: &str| c.name(name).map(|m| m.as_str());
+    Some(Offer {
+        item: group("item")?,
+        cost: group("cost"),
+        currency: group("currency"),
+        league: group("league")?,
+        stash: group("stash"),
+        left: group("left"),
+        top: group("top"),
+        // bugged
+        quality_item: QUALITY
+            .captures(text)
+            .and_then(|q| q.name("item"))
+            .map(|m| m.as_str()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whispers() {
+        let msg = r#"@From <TestGuild> 匚丹匚丹几丹: Hi, I would like to buy your Aegis Aurora Champion Kite Shield listed for 5 awakened-sextant in Ancestor (stash tab "~b/o 4.99 awakened-sextant"; position: left 9, top 7)"#;
+        let w = match parse(msg) {
+            Line::Whisper(w) => w,
+            other => panic!("not a whisper: {:?}", other),
+        };
+        assert_eq!(w.typ, TradeType::Incoming);
+        assert_eq!(w.guild, Some("TestGuild"));
+        assert_eq!(w.player, "匚丹匚丹几丹");
+        assert!(w.text.starts_with("Hi, I would like"));
+
+        assert_eq!(parse("some specific information"), Line::Other);
+        assert_eq!(parse("mail to someone@To:"), Line::Other);
+        assert_eq!(
+            parse("2023/10/13 01:55:01 1054481421 cffb0719 [INFO Client 30680] : That character is not online."),
+            Line::SystemReply(TradeStatus::Offline, "That character is not online.")
+        );
+        assert_eq!(
+            parse(": AFK mode is now ON. Autoreply \"brb\""),
+            Line::SystemReply(TradeStatus::Afk, "AFK mode is now ON. Autoreply \"brb\"")
+        );
+        assert_eq!(
+            parse("2023/10/13 01:55:01 1054481421 cffb0719 [INFO Client 30680] : You have joined global chat channel 820 English."),
+            Line::Other
+        );
+    }
+
+    #[test]
+    fn offers() {
+        assert_eq!(
+            parse_offer("Hi, I would like to buy your The Pandemonius, Jade Amulet listed for 4 divine in Ancestor (stash tab \"pub\"; position: left 11, top 1)"),
+            Some(Offer {
+                item: "The Pandemonius, Jade Amulet",
+                cost: Some("4"),
+                currency: Some("divine"),
+                league: "Ancestor",
+                stash: Some("pub"),
+                left: Some("11"),
+                top: Some("1"),
+                quality_item: None,
+            })
+        );
+        assert_eq!(
+            parse_offer("Hi, I would like to buy your Tabula Rasa Simple Robe in Standard"),
+            Some(Offer {
+                item: "Tabula Rasa Simple Robe",
+                cost: None,
+                currency: None,
+                league: "Standard",
+                stash: None,
+                left: None,
+                top: None,
+                quality_item: None,
+            })
+        );
+        let gem = parse_offer(
+            "Hi, I'd like to buy your 3 Orb of Alteration for my 1 Chaos Orb in Ancestor",
+        )
+        .unwrap();
+        assert_eq!((gem.item, gem.league), ("3 Orb of Alteration", "Ancestor"));
+        assert_eq!(parse_offer("wtb your stuff"), None);
+    }
+}
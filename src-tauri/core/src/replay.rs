@@ -0,0 +1,156 @@
+use crate::model::line_time;
+use chrono::NaiveDateTime;
+use std::{io::Write, time::Duration};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayOptions {
+    // 2.0 replays twice as fast, 0 doesn't wait at all
+    pub speed: f64,
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        ReplayOptions {
+            speed: 1.0,
+            from: None,
+            to: None,
+        }
+    }
+}
+
+// recorded lines with the pause before each one, lines without timestamp belong
+// to the previous line
+pub fn schedule(lines: &[String], opts: &ReplayOptions) -> Vec<(Duration, String)> {
+    let mut res = vec![];
+    let mut current: Option<NaiveDateTime> = None;
+    let mut prev: Option<NaiveDateTime> = None;
+    for l in lines {
+        if let Some(t) = line_time(l) {
+            current = Some(t);
+        }
+        let inside = match current {
+            Some(t) => {
+                opts.from.map_or(true, |from| t >= from) && opts.to.map_or(true, |to| t <= to)
+            }
+            None => opts.from.is_none(),
+        };
+        if !inside {
+            continue;
+        }
+
+        let pause = match (prev, current) {
+            (Some(p), Some(c)) if c > p && opts.speed > 0.0 => {
+                (c - p).to_std().unwrap_or_default().div_f64(opts.speed)
+            }
+            _ => Duration::ZERO,
+        };
+        if current.is_some() {
+            prev = current;
+        }
+        res.push((pause, l.clone()));
+    }
+    res
+}
+
+// appends scheduled lines to `out`, `sleep` is called with each pause
+pub fn replay<W: Write, S: FnMut(Duration)>(
+    schedule: Vec<(Duration, String)>,
+    out: &mut W,
+    mut sleep: S,
+) -> std::io::Result<()> {
+    for (pause, line) in schedule {
+        if !pause.is_zero() {
+            sleep(pause);
+        }
+        // whole line in one write so readers don't wake up for a half of it
+        out.write_all(format!("{}\n", line).as_bytes())?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorded() -> Vec<String> {
+        [
+            "header without timestamp",
+            "2023/10/13 01:00:00 1 a [INFO Client 1] first",
+            "2023/10/13 01:00:10 2 a [INFO Client 1] second",
+            "continuation of second",
+            "2023/10/13 01:00:12 3 a [INFO Client 1] third",
+            "2023/10/13 01:01:00 4 a [INFO Client 1] fourth",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+    }
+
+    fn time(s: &str) -> Option<NaiveDateTime> {
+        line_time(&format!("2023/10/13 {}", s))
+    }
+
+    fn pauses(sch: &[(Duration, String)]) -> Vec<u64> {
+        sch.iter().map(|(d, _)| d.as_millis() as u64).collect()
+    }
+
+    #[test]
+    fn timing_and_filters() {
+        let all = schedule(&recorded(), &ReplayOptions::default());
+        assert_eq!(pauses(&all), vec![0, 0, 10000, 0, 2000, 48000]);
+
+        let fast = schedule(
+            &recorded(),
+            &ReplayOptions {
+                speed: 4.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(pauses(&fast), vec![0, 0, 2500, 0, 500, 12000]);
+
+        let instant = schedule(
+            &recorded(),
+            &ReplayOptions {
+                speed: 0.0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(pauses(&instant), vec![0; 6]);
+
+        let window = schedule(
+            &recorded(),
+            &ReplayOptions {
+                speed: 1.0,
+                from: time("01:00:05"),
+                to: time("01:00:12"),
+            },
+        );
+        let lines: Vec<&str> = window.iter().map(|(_, l)| l.as_str()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "2023/10/13 01:00:10 2 a [INFO Client 1] second",
+                "continuation of second",
+                "2023/10/13 01:00:12 3 a [INFO Client 1] third",
+            ]
+        );
+        assert_eq!(pauses(&window), vec![0, 0, 2000]);
+    }
+
+    #[test]
+    fn writes_lines() {
+        let mut out = vec![];
+        let mut slept = vec![];
+        let sch = schedule(&recorded()[1..3], &ReplayOptions::default());
+        replay(sch, &mut out, |d| slept.push(d)).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "2023/10/13 01:00:00 1 a [INFO Client 1] first\n2023/10/13 01:00:10 2 a [INFO Client 1] second\n"
+        );
+        assert_eq!(slept, vec![Duration::from_secs(10)]);
+    }
+}
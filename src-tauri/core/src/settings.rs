@@ -0,0 +1,308 @@
+use crate::command_queue::QueueSettings;
+use crate::commands::DeliverySettings;
+use crate::file_line_reader::BackfillSettings;
+use crate::hotkeys::Hotkeys;
+use crate::ipc::camel_case;
+use crate::layout::Layouts;
+use crate::log_watcher::{new_watcher, touches_log, watch_dir, WatcherSettings};
+use crate::macros::{default_macros, ChatMacro};
+use crate::overlay::OverlaySettings;
+use anyhow::{bail, Context};
+use log::{debug, error, info, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::time::Duration;
+use thiserror::Error;
+
+// version of the config file written by this build, bumped with every migration
+pub const SETTINGS_VERSION: u32 = 2;
+
+type Migration = fn(&mut Map<String, Value>);
+
+// MIGRATIONS[n] upgrades a file of version n to n + 1, files written before
+// versioning are version 0
+const MIGRATIONS: [Migration; SETTINGS_VERSION as usize] = [positions_by_label, camel_case_fields];
+
+// incoming_position and outgoing_position moved into a map keyed by window label
+fn positions_by_label(cfg: &mut Map<String, Value>) {
+    let mut positions = Map::new();
+    for label in ["incoming", "outgoing"] {
+        if let Some(p) = cfg.remove(&format!("{}_position", label)) {
+            positions.insert(label.to_string(), p);
+        }
+    }
+    cfg.insert("positions".to_string(), Value::Object(positions));
+}
+
+// watcher, overlay, hotkeys and macros are sent to the webviews as saved, they took
+// the camelCase of the other ipc payloads for field names and enum values alike
+fn camel_case_fields(cfg: &mut Map<String, Value>) {
+    fn rename_keys(o: &mut Map<String, Value>) {
+        *o = std::mem::take(o)
+            .into_iter()
+            .map(|(k, v)| (camel_case(&k), v))
+            .collect();
+    }
+    for key in ["watcher", "overlay", "hotkeys"] {
+        if let Some(Value::Object(o)) = cfg.get_mut(key) {
+            rename_keys(o);
+        }
+    }
+    let macros = match cfg.get_mut("macros") {
+        Some(Value::Array(macros)) => macros,
+        _ => return,
+    };
+    for m in macros.iter_mut().filter_map(Value::as_object_mut) {
+        rename_keys(m);
+        if let Some(Value::String(t)) = m.get_mut("tradeType") {
+            *t = t.to_lowercase();
+        }
+        if let Some(Value::Array(steps)) = m.get_mut("steps") {
+            for step in steps.iter_mut().filter_map(Value::as_object_mut) {
+                rename_keys(step);
+                // commands with an argument are tagged with single words
+                if let Some(Value::String(c)) = step.get_mut("command") {
+                    *c = camel_case(c);
+                }
+            }
+        }
+    }
+}
+
+// lowest poll interval the settings page offers
+pub const MIN_POLL_INTERVAL_MS: u64 = 50;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SettingsError {
+    #[error("poll interval of {0} ms is below {} ms", MIN_POLL_INTERVAL_MS)]
+    PollInterval(u64),
+    #[error("macro without a name")]
+    UnnamedMacro,
+    #[error("more than one macro named {0}")]
+    DuplicateMacro(String),
+    #[error("settings are from a newer build (version {0}), not overwriting them")]
+    NewerVersion(u32),
+}
+
+// every field has a default, a file missing some of them still loads
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub logpath: String,
+    // physical positions by window label from before layouts, used when there is no layout yet
+    #[serde(default)]
+    pub positions: BTreeMap<String, (i32, i32)>,
+    #[serde(default)]
+    pub layouts: Layouts,
+    #[serde(default = "default_macros")]
+    pub macros: Vec<ChatMacro>,
+    #[serde(default)]
+    pub hotkeys: Hotkeys,
+    #[serde(default)]
+    pub command_queue: QueueSettings,
+    #[serde(default)]
+    pub delivery: DeliverySettings,
+    #[serde(default)]
+    pub backfill: BackfillSettings,
+    #[serde(default)]
+    pub watcher: WatcherSettings,
+    #[serde(default)]
+    pub overlay: OverlaySettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            version: SETTINGS_VERSION,
+            logpath: String::new(),
+            positions: BTreeMap::new(),
+            layouts: Layouts::default(),
+            macros: default_macros(),
+            hotkeys: Hotkeys::default(),
+            command_queue: QueueSettings::default(),
+            delivery: DeliverySettings::default(),
+            backfill: BackfillSettings::default(),
+            watcher: WatcherSettings::default(),
+            overlay: OverlaySettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    // loads a config written by any build: older files are backed up to
+    // `<p>.v<version>.bak` and rewritten in the current version, newer ones are only read,
+    // saving them would drop what this build doesn't know
+    pub fn new(p: &str) -> anyhow::Result<Self> {
+        let raw = std::fs::read_to_string(p).with_context(|| format!("can't read {}", p))?;
+        let mut cfg =
+            match serde_json::from_str(&raw).with_context(|| format!("can't parse {}", p))? {
+                Value::Object(cfg) => cfg,
+                _ => bail!("{} is not a settings object", p),
+            };
+        let version = cfg.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+        if version >= SETTINGS_VERSION {
+            if version > SETTINGS_VERSION {
+                error!(
+                    "{} is from a newer build (version {}), loading what this build knows without saving",
+                    p, version
+                );
+            }
+            let mut s: Settings = serde_json::from_value(Value::Object(cfg))?;
+            s.repair(p);
+            s.validate()?;
+            return Ok(s);
+        }
+
+        let backup = format!("{}.v{}.bak", p, version);
+        std::fs::write(&backup, &raw).with_context(|| format!("can't back up {}", p))?;
+        info!(
+            "migrating {} from version {}, backed up to {}",
+            p, version, backup
+        );
+        for migrate in MIGRATIONS[version as usize..].iter() {
+            migrate(&mut cfg);
+        }
+        cfg.insert("version".to_string(), SETTINGS_VERSION.into());
+        let mut s: Settings = serde_json::from_value(Value::Object(cfg))?;
+        s.repair(p);
+        s.save(p)?;
+        Ok(s)
+    }
+
+    // values an older build or a hand edit allowed are fixed instead of rejecting the file,
+    // the app would start on defaults and save them over everything else in it
+    fn repair(&mut self, p: &str) {
+        if self.watcher.poll_interval_ms < MIN_POLL_INTERVAL_MS {
+            warn!(
+                "{}: raising poll interval of {} ms to {} ms",
+                p, self.watcher.poll_interval_ms, MIN_POLL_INTERVAL_MS
+            );
+            self.watcher.poll_interval_ms = MIN_POLL_INTERVAL_MS;
+        }
+        let mut names: HashSet<String> = HashSet::new();
+        for (i, m) in self.macros.iter_mut().enumerate() {
+            let base = match m.name.trim() {
+                "" => format!("macro {}", i + 1),
+                name => name.to_string(),
+            };
+            let mut name = base.clone();
+            let mut n = 1;
+            while names.contains(&name) {
+                n += 1;
+                name = format!("{} ({})", base, n);
+            }
+            if name != m.name {
+                warn!("{}: renaming macro {:?} to {:?}", p, m.name, name);
+                m.name = name.clone();
+            }
+            names.insert(name);
+        }
+    }
+
+    // settings that can't be run with, they aren't saved
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.watcher.poll_interval_ms < MIN_POLL_INTERVAL_MS {
+            return Err(SettingsError::PollInterval(self.watcher.poll_interval_ms));
+        }
+        let mut names = HashSet::new();
+        for m in self.macros.iter() {
+            if m.name.trim().is_empty() {
+                return Err(SettingsError::UnnamedMacro);
+            }
+            if !names.insert(m.name.as_str()) {
+                return Err(SettingsError::DuplicateMacro(m.name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    // a crash while saving leaves the old file
+    pub fn save(&self, p: &str) -> anyhow::Result<()> {
+        if self.version > SETTINGS_VERSION {
+            return Err(SettingsError::NewerVersion(self.version).into());
+        }
+        self.validate()?;
+        write_atomic(Path::new(p), &serde_json::to_vec(self)?)
+    }
+
+    // names of the fields that differ from `other`
+    pub fn changes(&self, other: &Settings) -> Vec<&'static str> {
+        let fields = [
+            ("logpath", self.logpath != other.logpath),
+            ("positions", self.positions != other.positions),
+            ("layouts", self.layouts != other.layouts),
+            ("macros", self.macros != other.macros),
+            ("hotkeys", self.hotkeys != other.hotkeys),
+            ("command_queue", self.command_queue != other.command_queue),
+            ("delivery", self.delivery != other.delivery),
+            ("backfill", self.backfill != other.backfill),
+            ("watcher", self.watcher != other.watcher),
+            ("overlay", self.overlay != other.overlay),
+        ];
+        fields
+            .iter()
+            .filter(|(_, changed)| *changed)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+// written next to `p` and renamed over it, readers see either the old or the new file
+pub fn write_atomic(p: &Path, data: &[u8]) -> anyhow::Result<()> {
+    let mut tmp = p.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut f =
+        std::fs::File::create(&tmp).with_context(|| format!("can't create {}", tmp.display()))?;
+    f.write_all(data)?;
+    f.sync_all()?;
+    std::fs::rename(&tmp, p).with_context(|| format!("can't replace {}", p.display()))?;
+    Ok(())
+}
+
+// quiet time after the last change before the file is read, editors and our own saves
+// write it in several steps
+const RELOAD_DELAY: Duration = Duration::from_millis(200);
+
+// calls `on_change` once the file was written, saves of the app included, so the app
+// compares what it loads with what it runs; the directory is watched since saves
+// replace the file
+pub struct SettingsWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl SettingsWatcher {
+    pub fn new<F>(p: &str, on_change: F) -> notify::Result<Self>
+    where
+        F: Fn() + Send + 'static,
+    {
+        let (tx, rx) = channel();
+        let path = p.to_string();
+        let mut watcher = new_watcher(move |ev| match ev {
+            Ok(paths) if touches_log(&paths, &path) => {
+                let _ = tx.send(());
+            }
+            Ok(_) => {}
+            Err(e) => warn!("settings watcher failed: {}", e),
+        })?;
+        watcher.watch(&watch_dir(p), RecursiveMode::NonRecursive)?;
+
+        // ends once the watcher is dropped along with the sender
+        let path = p.to_string();
+        std::thread::spawn(move || {
+            while rx.recv().is_ok() {
+                while rx.recv_timeout(RELOAD_DELAY).is_ok() {}
+                debug!("{} changed", path);
+                on_change();
+            }
+        });
+        Ok(SettingsWatcher { _watcher: watcher })
+    }
+}
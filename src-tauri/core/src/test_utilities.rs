@@ -0,0 +1,86 @@
+use crate::command_queue::Clock;
+use crate::commands::Dispatcher;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[derive(Clone)]
+pub struct Callable {
+    m: Arc<Mutex<u64>>,
+}
+
+impl Callable {
+    pub fn new() -> Self {
+        Callable {
+            m: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    pub fn call(&self) {
+        *self.m.lock().unwrap() += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        *self.m.lock().unwrap()
+    }
+}
+
+#[derive(Clone)]
+pub struct FakeClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl FakeClock {
+    pub fn new() -> Self {
+        FakeClock {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    pub fn advance(&self, d: Duration) {
+        *self.now.lock().unwrap() += d;
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
+
+// records every dispatched line, can be told to fail next calls
+#[derive(Clone)]
+pub struct RecordingDispatcher {
+    lines: Arc<Mutex<Vec<String>>>,
+    failures: Arc<Mutex<u32>>,
+}
+
+impl RecordingDispatcher {
+    pub fn new() -> Self {
+        RecordingDispatcher {
+            lines: Arc::new(Mutex::new(vec![])),
+            failures: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    pub fn fail_next(&self, n: u32) {
+        *self.failures.lock().unwrap() = n;
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().clone()
+    }
+}
+
+impl Dispatcher for RecordingDispatcher {
+    fn dispatch(&mut self, line: &str) -> anyhow::Result<()> {
+        let mut failures = self.failures.lock().unwrap();
+        if *failures > 0 {
+            *failures -= 1;
+            anyhow::bail!("dispatch failed");
+        }
+        self.lines.lock().unwrap().push(line.to_string());
+        Ok(())
+    }
+}
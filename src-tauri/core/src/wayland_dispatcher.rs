@@ -0,0 +1,47 @@
+use crate::commands::{DeliveryMode, DeliverySettings, Dispatcher};
+use anyhow::{bail, Context};
+use log::debug;
+use std::process::Command;
+
+// fallback for compositors that don't accept XTest input, relies on `wtype` and `wl-copy`.
+// there is no portable way to focus other windows on wayland, so game has to be focused already
+pub struct WaylandDispatcher {
+    settings: DeliverySettings,
+}
+
+impl WaylandDispatcher {
+    pub fn new(settings: DeliverySettings) -> Self {
+        WaylandDispatcher { settings }
+    }
+
+    fn wtype(&self, args: &[&str]) -> anyhow::Result<()> {
+        let delay = self.settings.key_delay_ms.to_string();
+        run(Command::new("wtype").args(["-d", &delay]).args(args))
+    }
+}
+
+fn run(cmd: &mut Command) -> anyhow::Result<()> {
+    let program = cmd.get_program().to_string_lossy().to_string();
+    let status = cmd
+        .status()
+        .with_context(|| format!("can't run {}, is it installed?", program))?;
+    if !status.success() {
+        bail!("{} exited with {}", program, status);
+    }
+    Ok(())
+}
+
+impl Dispatcher for WaylandDispatcher {
+    fn dispatch(&mut self, line: &str) -> anyhow::Result<()> {
+        debug!("deliver chat command through wtype: {}", line);
+        self.wtype(&["-k", "Return"])?;
+        match self.settings.mode {
+            DeliveryMode::Type => self.wtype(&["--", line])?,
+            DeliveryMode::Paste => {
+                run(Command::new("wl-copy").args(["--", line]))?;
+                self.wtype(&["-M", "ctrl", "v", "-m", "ctrl"])?;
+            }
+        }
+        self.wtype(&["-k", "Return"])
+    }
+}
@@ -0,0 +1,431 @@
+use crate::commands::{DeliveryMode, DeliverySettings, Dispatcher};
+use anyhow::{anyhow, Context};
+use log::{debug, error};
+use std::{thread::sleep, time::Duration};
+use x11rb::{
+    connection::{Connection, RequestConnection},
+    protocol::{
+        xproto::{
+            Atom, AtomEnum, ClientMessageEvent, ConfigureWindowAux, ConnectionExt as _, EventMask,
+            InputFocus, Keycode, Keysym, StackMode, Window, KEY_PRESS_EVENT, KEY_RELEASE_EVENT,
+        },
+        xtest::ConnectionExt as _,
+    },
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+    CURRENT_TIME, NONE,
+};
+
+const XK_RETURN: Keysym = 0xff0d;
+const XK_SHIFT_L: Keysym = 0xffe1;
+const XK_CONTROL_L: Keysym = 0xffe3;
+const XK_V: Keysym = 0x0076;
+
+// clients that read key events late, like wine, look up the keysym of a remapped spare
+// keycode only when handling the event, it's kept bound for that long
+const SPARE_RESTORE_DELAY: Duration = Duration::from_millis(100);
+// the game asks for the clipboard contents when it handles ctrl+v
+const CLIPBOARD_RESTORE_DELAY: Duration = Duration::from_millis(200);
+
+// keysym of a character, latin-1 maps directly, everything else uses unicode keysyms
+fn char_keysym(c: char) -> Keysym {
+    match c as u32 {
+        cp @ (0x20..=0x7e | 0xa0..=0xff) => cp,
+        cp => 0x0100_0000 | cp,
+    }
+}
+
+struct Keymap {
+    min_keycode: Keycode,
+    per_keycode: usize,
+    keysyms: Vec<Keysym>,
+    // keycode without any keysyms, temporarily bound to characters missing from the layout
+    spare: Option<Keycode>,
+}
+
+impl Keymap {
+    fn load(conn: &RustConnection) -> anyhow::Result<Self> {
+        let setup = conn.setup();
+        let (min, max) = (setup.min_keycode, setup.max_keycode);
+        let reply = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+        let per_keycode = reply.keysyms_per_keycode as usize;
+        let spare = reply
+            .keysyms
+            .chunks(per_keycode)
+            .rposition(|ks| ks.iter().all(|k| *k == 0))
+            .map(|i| min + i as u8);
+        Ok(Keymap {
+            min_keycode: min,
+            per_keycode,
+            keysyms: reply.keysyms,
+            spare,
+        })
+    }
+
+    // keycode and whether shift should be held
+    fn lookup(&self, keysym: Keysym) -> Option<(Keycode, bool)> {
+        for level in 0..self.per_keycode.min(2) {
+            let found = self
+                .keysyms
+                .chunks(self.per_keycode)
+                .position(|ks| ks[level] == keysym);
+            if let Some(i) = found {
+                return Some((self.min_keycode + i as u8, level == 1));
+            }
+        }
+        None
+    }
+}
+
+pub struct X11Dispatcher {
+    conn: RustConnection,
+    root: Window,
+    keymap: Keymap,
+    net_wm_name: Atom,
+    net_active_window: Atom,
+    utf8_string: Atom,
+    settings: DeliverySettings,
+    clipboard: Option<arboard::Clipboard>,
+}
+
+impl X11Dispatcher {
+    // `display` is taken from DISPLAY environment variable when None
+    pub fn connect(display: Option<&str>, settings: DeliverySettings) -> anyhow::Result<Self> {
+        let (conn, screen) = x11rb::connect(display).context("can't connect to X server")?;
+        conn.extension_information(x11rb::protocol::xtest::X11_EXTENSION_NAME)?
+            .ok_or(anyhow!("X server doesn't support XTEST extension"))?;
+        let root = conn.setup().roots[screen].root;
+        let keymap = Keymap::load(&conn)?;
+
+        let atom = |name: &[u8]| -> anyhow::Result<Atom> {
+            Ok(conn.intern_atom(false, name)?.reply()?.atom)
+        };
+        let net_wm_name = atom(b"_NET_WM_NAME")?;
+        let net_active_window = atom(b"_NET_ACTIVE_WINDOW")?;
+        let utf8_string = atom(b"UTF8_STRING")?;
+
+        let clipboard = match settings.mode {
+            DeliveryMode::Paste => Some(arboard::Clipboard::new()?),
+            DeliveryMode::Type => None,
+        };
+
+        Ok(X11Dispatcher {
+            conn,
+            root,
+            keymap,
+            net_wm_name,
+            net_active_window,
+            utf8_string,
+            settings,
+            clipboard,
+        })
+    }
+
+    fn window_title(&self, window: Window) -> anyhow::Result<Option<String>> {
+        for (property, typ) in [
+            (self.net_wm_name, self.utf8_string),
+            (AtomEnum::WM_NAME.into(), AtomEnum::STRING.into()),
+        ] {
+            let reply = self
+                .conn
+                .get_property(false, window, property, typ, 0, 1024)?
+                .reply()?;
+            if !reply.value.is_empty() {
+                return Ok(Some(String::from_utf8_lossy(&reply.value).to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    fn find_game_window(&self) -> anyhow::Result<Window> {
+        let mut stack = vec![self.root];
+        while let Some(window) = stack.pop() {
+            if self.window_title(window)?.as_deref() == Some(self.settings.window_title.as_str()) {
+                return Ok(window);
+            }
+            stack.extend(self.conn.query_tree(window)?.reply()?.children);
+        }
+        Err(anyhow!(
+            "window \"{}\" not found",
+            self.settings.window_title
+        ))
+    }
+
+    fn activate(&self, window: Window) -> anyhow::Result<()> {
+        // ask window manager first, plain focus request is enough without one
+        let event = ClientMessageEvent::new(32, window, self.net_active_window, [2, 0, 0, 0, 0]);
+        self.conn.send_event(
+            false,
+            self.root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        )?;
+        self.conn.configure_window(
+            window,
+            &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE),
+        )?;
+        self.conn
+            .set_input_focus(InputFocus::PARENT, window, CURRENT_TIME)?
+            .check()
+            .context("can't focus game window")?;
+        Ok(())
+    }
+
+    fn key(&self, keycode: Keycode, press: bool) -> anyhow::Result<()> {
+        let typ = if press {
+            KEY_PRESS_EVENT
+        } else {
+            KEY_RELEASE_EVENT
+        };
+        self.conn
+            .xtest_fake_input(typ, keycode, CURRENT_TIME, NONE, 0, 0, 0)?;
+        self.conn.flush()?;
+        sleep(Duration::from_millis(self.settings.key_delay_ms));
+        Ok(())
+    }
+
+    fn tap(&self, keysym: Keysym, modifiers: &[Keysym]) -> anyhow::Result<()> {
+        let (keycode, shift) = self
+            .keymap
+            .lookup(keysym)
+            .ok_or(anyhow!("no key for keysym {:#x}", keysym))?;
+        let mut held = modifiers
+            .iter()
+            .map(|m| self.keymap.lookup(*m).map(|(k, _)| k))
+            .collect::<Option<Vec<Keycode>>>()
+            .ok_or(anyhow!("no modifier keys in keyboard layout"))?;
+        if shift {
+            held.push(
+                self.keymap
+                    .lookup(XK_SHIFT_L)
+                    .ok_or(anyhow!("no shift key in keyboard layout"))?
+                    .0,
+            );
+        }
+
+        for m in held.iter() {
+            self.key(*m, true)?;
+        }
+        self.key(keycode, true)?;
+        self.key(keycode, false)?;
+        for m in held.iter().rev() {
+            self.key(*m, false)?;
+        }
+        Ok(())
+    }
+
+    fn type_char(&self, c: char) -> anyhow::Result<()> {
+        let keysym = char_keysym(c);
+        if self.keymap.lookup(keysym).is_some() {
+            return self.tap(keysym, &[]);
+        }
+
+        let spare = self
+            .keymap
+            .spare
+            .ok_or(anyhow!("can't type {:?}, no spare keycode to remap", c))?;
+        let mut mapping = vec![0; self.keymap.per_keycode];
+        mapping[0] = keysym;
+        if mapping.len() > 1 {
+            mapping[1] = keysym;
+        }
+        self.remap(spare, &mapping)?;
+        let typed = self
+            .key(spare, true)
+            .and_then(|_| self.key(spare, false))
+            .and_then(|_| Ok(self.conn.sync()?));
+        sleep(SPARE_RESTORE_DELAY);
+        // spare keycode goes back to having no keysyms, also when typing failed
+        let start = (spare - self.keymap.min_keycode) as usize * self.keymap.per_keycode;
+        let original = &self.keymap.keysyms[start..start + self.keymap.per_keycode];
+        self.remap(spare, original)?;
+        typed
+    }
+
+    fn remap(&self, keycode: Keycode, keysyms: &[Keysym]) -> anyhow::Result<()> {
+        self.conn
+            .change_keyboard_mapping(1, keycode, self.keymap.per_keycode as u8, keysyms)?
+            .check()?;
+        Ok(())
+    }
+}
+
+impl Dispatcher for X11Dispatcher {
+    fn dispatch(&mut self, line: &str) -> anyhow::Result<()> {
+        let window = self.find_game_window()?;
+        debug!("deliver chat command to window {:#x}: {}", window, line);
+        self.activate(window)?;
+        sleep(Duration::from_millis(self.settings.focus_delay_ms));
+
+        self.tap(XK_RETURN, &[])?;
+        // user's clipboard is put back after the paste, None when it held no text
+        let previous = match self.clipboard.as_mut() {
+            Some(clipboard) => {
+                let previous = clipboard.get_text().ok();
+                clipboard.set_text(line.to_string())?;
+                Some(previous)
+            }
+            None => None,
+        };
+        let typed = match previous {
+            Some(_) => self.tap(XK_V, &[XK_CONTROL_L]),
+            None => line.chars().try_for_each(|c| self.type_char(c)),
+        }
+        .and_then(|_| self.tap(XK_RETURN, &[]))
+        .and_then(|_| Ok(self.conn.sync()?));
+
+        if let (Some(previous), Some(clipboard)) = (previous, self.clipboard.as_mut()) {
+            sleep(CLIPBOARD_RESTORE_DELAY);
+            let restored = match previous {
+                Some(text) => clipboard.set_text(text),
+                None => clipboard.clear(),
+            };
+            if let Err(e) = restored {
+                error!("can't restore clipboard: {}", e);
+            }
+        }
+        typed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Child, Command};
+    use x11rb::protocol::{
+        xproto::{CreateWindowAux, PropMode, WindowClass},
+        Event,
+    };
+
+    struct Xvfb(Child);
+
+    impl Drop for Xvfb {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    // starts headless X server
+    fn xvfb(display: u32) -> Xvfb {
+        let child = Command::new("Xvfb")
+            .arg(format!(":{}", display))
+            .args(["-screen", "0", "640x480x24", "-nolisten", "tcp"])
+            .spawn()
+            .expect("can't start Xvfb, is it installed?");
+        let server = Xvfb(child);
+        for _ in 0..50 {
+            if x11rb::connect(Some(&format!(":{}", display))).is_ok() {
+                return server;
+            }
+            sleep(Duration::from_millis(100));
+        }
+        panic!("Xvfb on :{} didn't come up", display);
+    }
+
+    fn dummy_window(conn: &RustConnection, screen: usize, title: &str) -> Window {
+        let screen = &conn.setup().roots[screen];
+        let window = conn.generate_id().unwrap();
+        conn.create_window(
+            screen.root_depth,
+            window,
+            screen.root,
+            0,
+            0,
+            100,
+            100,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            screen.root_visual,
+            &CreateWindowAux::new().event_mask(EventMask::KEY_PRESS | EventMask::KEY_RELEASE),
+        )
+        .unwrap();
+        let net_wm_name = conn
+            .intern_atom(false, b"_NET_WM_NAME")
+            .unwrap()
+            .reply()
+            .unwrap()
+            .atom;
+        let utf8 = conn
+            .intern_atom(false, b"UTF8_STRING")
+            .unwrap()
+            .reply()
+            .unwrap()
+            .atom;
+        conn.change_property8(
+            PropMode::REPLACE,
+            window,
+            net_wm_name,
+            utf8,
+            title.as_bytes(),
+        )
+        .unwrap();
+        conn.map_window(window).unwrap();
+        conn.sync().unwrap();
+        window
+    }
+
+    // run by the core job in .github/workflows/tests.yml
+    #[test]
+    #[ignore = "needs Xvfb, run with --ignored"]
+    fn types_into_game_window() {
+        let display = 97;
+        let _server = xvfb(display);
+        let name = format!(":{}", display);
+        let (conn, screen) = x11rb::connect(Some(&name)).unwrap();
+        let window = dummy_window(&conn, screen, "Path of Exile");
+        dummy_window(&conn, screen, "Path of Exile Wiki");
+
+        let settings = DeliverySettings {
+            key_delay_ms: 0,
+            focus_delay_ms: 0,
+            ..Default::default()
+        };
+        let mut dispatcher = X11Dispatcher::connect(Some(&name), settings).unwrap();
+        dispatcher.dispatch("/invite Ab_1").unwrap();
+
+        assert_eq!(
+            conn.get_input_focus().unwrap().reply().unwrap().focus,
+            window
+        );
+
+        let keymap = Keymap::load(&conn).unwrap();
+        let mut typed = String::new();
+        let mut shift = false;
+        while let Some(ev) = conn.poll_for_event().unwrap() {
+            let (detail, press) = match ev {
+                Event::KeyPress(e) if e.event == window => (e.detail, true),
+                Event::KeyRelease(e) if e.event == window => (e.detail, false),
+                _ => continue,
+            };
+            let idx = (detail - keymap.min_keycode) as usize * keymap.per_keycode;
+            let ks = keymap.keysyms[idx];
+            if ks == XK_SHIFT_L {
+                shift = press;
+            } else if press && ks == XK_RETURN {
+                typed.push('\n');
+            } else if press {
+                let ks = keymap.keysyms[idx + shift as usize];
+                typed.push(char::from_u32(ks).unwrap());
+            }
+        }
+        assert_eq!(typed, "\n/invite Ab_1\n");
+
+        // characters missing from the layout borrow the spare keycode and give it back
+        let spare = keymap.spare.unwrap();
+        dispatcher.dispatch("€").unwrap();
+        let mut pressed = false;
+        while let Some(ev) = conn.poll_for_event().unwrap() {
+            pressed |= matches!(ev, Event::KeyPress(e) if e.event == window && e.detail == spare);
+        }
+        assert!(pressed);
+        assert_eq!(Keymap::load(&conn).unwrap().keysyms, keymap.keysyms);
+    }
+
+    #[test]
+    fn keysyms() {
+        assert_eq!(char_keysym('a'), 0x61);
+        assert_eq!(char_keysym('é'), 0xe9);
+        assert_eq!(char_keysym('匚'), 0x0100_531a);
+    }
+}
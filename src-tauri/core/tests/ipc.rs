@@ -0,0 +1,121 @@
+// generated bindings, the app's handlers and the webviews are checked against the ipc contract
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+use trade_core::ipc::{self, IPC_VERSION};
+
+fn repo() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../..")
+}
+
+fn bindings_path() -> PathBuf {
+    repo().join("src/lib/ipc.ts")
+}
+
+fn version(bindings: &str) -> Option<u32> {
+    bindings
+        .lines()
+        .find_map(|l| l.strip_prefix("export const IPC_VERSION = "))
+        .and_then(|v| v.trim_end_matches(';').parse().ok())
+}
+
+fn shapes(bindings: &str) -> Vec<&str> {
+    bindings
+        .lines()
+        .filter(|l| !l.starts_with("export const IPC_VERSION"))
+        .collect()
+}
+
+#[test]
+fn bindings_match_contract() {
+    let generated = ipc::bindings();
+    let committed = fs::read_to_string(bindings_path()).unwrap_or_default();
+    if generated == committed {
+        return;
+    }
+    assert!(
+        shapes(&generated) == shapes(&committed) || version(&committed) != Some(IPC_VERSION),
+        "ipc payloads changed shape, bump IPC_VERSION in core/src/ipc.rs"
+    );
+    if std::env::var_os("UPDATE_IPC").is_some() {
+        fs::write(bindings_path(), generated).unwrap();
+    } else {
+        panic!("src/lib/ipc.ts is out of date, regenerate it with UPDATE_IPC=1");
+    }
+}
+
+#[test]
+fn app_registers_contract_commands() {
+    let main = fs::read_to_string(repo().join("src-tauri/src/main.rs")).unwrap();
+    let start = main.find("generate_handler![").unwrap() + "generate_handler![".len();
+    let end = start + main[start..].find(']').unwrap();
+    let registered: BTreeSet<&str> = main[start..end]
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let commands = ipc::commands();
+    let contract: BTreeSet<&str> = commands.iter().map(|c| c.name).collect();
+    assert_eq!(registered, contract);
+}
+
+fn sources(dir: &Path, out: &mut Vec<(PathBuf, String)>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            sources(&path, out);
+        } else if matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("svelte" | "js")
+        ) {
+            let text = fs::read_to_string(&path).unwrap();
+            out.push((path, text));
+        }
+    }
+}
+
+// names in `name('...'` calls
+fn called<'a>(text: &'a str, name: &str) -> Vec<&'a str> {
+    let call = format!("{}('", name);
+    text.match_indices(&call)
+        .filter_map(|(i, _)| text[i + call.len()..].split('\'').next())
+        .collect()
+}
+
+#[test]
+fn webviews_use_contract_names() {
+    let mut files = vec![];
+    sources(&repo().join("src"), &mut files);
+    let commands: Vec<&str> = ipc::commands().iter().map(|c| c.name).collect();
+    let events: Vec<&str> = ipc::events().iter().map(|e| e.name).collect();
+    for (path, text) in files.iter() {
+        // untyped tauri api would skip the contract
+        assert!(
+            !text.contains("from '@tauri-apps/api/tauri'")
+                && !text.contains("from '@tauri-apps/api/event'"),
+            "{} should import invoke, listen and emit from $lib/ipc",
+            path.display()
+        );
+        for c in called(text, "invoke") {
+            assert!(
+                commands.contains(&c),
+                "{}: unknown command {}",
+                path.display(),
+                c
+            );
+        }
+        for e in called(text, "listen")
+            .into_iter()
+            .chain(called(text, "emit"))
+        {
+            assert!(
+                events.contains(&e),
+                "{}: unknown event {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
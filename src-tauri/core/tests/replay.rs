@@ -0,0 +1,74 @@
+// recorded log replayed into a watched file goes through the same pipeline as the game's log
+use std::{
+    sync::{mpsc::channel, Arc, Mutex},
+    time::{Duration, Instant},
+};
+use trade_core::log_watcher;
+use trade_core::replay::{self, ReplayOptions};
+use trade_core::{FileLineReader, LogFile, Model};
+
+const RECORDED: &str = r#"2023/10/13 01:54:40 1054460421 cffb0719 [INFO Client 30680] Connecting to instance server
+2023/10/13 01:54:50 1054470421 cffb0719 [INFO Client 30680] @From buyer: Hi, I would like to buy your Tabula Rasa Simple Robe listed for 8 chaos in Ancestor (stash tab "~price 8 chaos"; position: left 1, top 1)
+2023/10/13 01:54:51 1054471421 cffb0719 [INFO Client 30680] @To seller: Hi, I would like to buy your Goldrim Leather Cap listed for 1 chaos in Ancestor (stash tab "~price 1 chaos"; position: left 1, top 1)
+2023/10/13 01:54:53 1054473421 cffb0719 [INFO Client 30680] @From buyer: nvm found another
+2023/10/13 01:55:30 1054510421 cffb0719 [INFO Client 30680] @From late: Hi, I would like to buy your Wanderlust Wool Shoes listed for 1 chaos in Ancestor (stash tab "~price 1 chaos"; position: left 2, top 1)"#;
+
+#[test]
+fn replay_through_watcher_reader_and_model() {
+    let dir = std::env::temp_dir().join(format!("replay-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("Client.txt");
+    std::fs::write(&target, b"").unwrap();
+    let target = target.to_str().unwrap().to_string();
+
+    let events = Arc::new(Mutex::new(vec![]));
+    let mut model = Model::new();
+    {
+        let e = Arc::clone(&events);
+        model.incoming_subscribe(move |t| {
+            e.lock()
+                .unwrap()
+                .push(format!("incoming {}", t.player_name()))
+        });
+        let e = Arc::clone(&events);
+        model.outgoing_subscribe(move |t| {
+            e.lock()
+                .unwrap()
+                .push(format!("outgoing {}", t.player_name()))
+        });
+        let e = Arc::clone(&events);
+        model.closed_subscribe(move |_| e.lock().unwrap().push("closed".to_string()));
+    }
+    let model = Arc::new(Mutex::new(model));
+    let mut reader = FileLineReader::<LogFile>::with_file(Arc::clone(&model), &target).unwrap();
+
+    let (tx, rx) = channel();
+    let _watcher = log_watcher::watch_log(tx, &target).unwrap();
+
+    let lines: Vec<String> = RECORDED.lines().map(|l| l.to_string()).collect();
+    let options = ReplayOptions {
+        speed: 20.0,
+        to: trade_core::model::line_time("2023/10/13 01:55:00"),
+        ..Default::default()
+    };
+    let schedule = replay::schedule(&lines, &options);
+    let out = target.clone();
+    let player = std::thread::spawn(move || {
+        let mut f = std::fs::OpenOptions::new().append(true).open(out).unwrap();
+        replay::replay(schedule, &mut f, std::thread::sleep).unwrap();
+    });
+
+    let expected = vec!["incoming buyer", "outgoing seller", "closed"];
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while events.lock().unwrap().len() < expected.len() && Instant::now() < deadline {
+        if let Ok(Ok(ev)) = rx.recv_timeout(Duration::from_millis(100)) {
+            if log_watcher::touches_log(&ev, &target) {
+                reader.process_new_content().unwrap();
+            }
+        }
+    }
+    player.join().unwrap();
+
+    assert_eq!(*events.lock().unwrap(), expected);
+    let _ = std::fs::remove_dir_all(&dir);
+}
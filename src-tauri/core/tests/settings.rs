@@ -0,0 +1,182 @@
+// config files written by past builds, kept in tests/fixtures/settings, load into the
+// current settings without losing what the user set up
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::time::Duration;
+use trade_core::commands::ChatCommand;
+use trade_core::layout::Monitor;
+use trade_core::log_watcher::WatcherMode;
+use trade_core::model::TradeType;
+use trade_core::settings::{
+    Settings, SettingsError, SettingsWatcher, MIN_POLL_INTERVAL_MS, SETTINGS_VERSION,
+};
+
+// copy of a fixture in a directory of its own, loading may rewrite it
+fn load(fixture: &str) -> (Settings, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("settings-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let cfg = dir.join("config.json");
+    let src = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/settings")
+        .join(fixture);
+    std::fs::copy(src, &cfg).unwrap();
+    let s = Settings::new(cfg.to_str().unwrap()).unwrap();
+    (s, cfg)
+}
+
+fn backups(cfg: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(cfg.parent().unwrap())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|n| n.ends_with(".bak"))
+        .collect();
+    names.sort();
+    names
+}
+
+fn saved_version(cfg: &Path) -> u64 {
+    let v: serde_json::Value = serde_json::from_slice(&std::fs::read(cfg).unwrap()).unwrap();
+    v["version"].as_u64().unwrap()
+}
+
+#[test]
+fn initial_config_is_migrated() {
+    let (s, cfg) = load("v0-initial.json");
+    assert!(s.logpath.ends_with("Client.txt"));
+    assert_eq!(s.positions["incoming"], (1480, 120));
+    assert_eq!(s.positions["outgoing"], (900, 0));
+    // fields added later start from their defaults
+    assert!(s.overlay.always_on_top);
+    assert!(!s.macros.is_empty());
+
+    assert_eq!(backups(&cfg), vec!["config.json.v0.bak"]);
+    assert_eq!(saved_version(&cfg), SETTINGS_VERSION as u64);
+    // migrated file loads as it is
+    let again = Settings::new(cfg.to_str().unwrap()).unwrap();
+    assert_eq!(again.positions, s.positions);
+    assert_eq!(backups(&cfg), vec!["config.json.v0.bak"]);
+}
+
+#[test]
+fn unversioned_config_keeps_everything() {
+    let (s, cfg) = load("v0.json");
+    assert_eq!(s.positions["incoming"], (1480, 120));
+    assert_eq!(s.hotkeys.invite.as_deref(), Some("Alt+1"));
+    assert_eq!(s.macros.len(), 1);
+    assert_eq!(s.watcher.mode, WatcherMode::Poll);
+    assert_eq!(s.watcher.poll_interval_ms, 500);
+    assert!(s.overlay.click_through);
+    let monitor = Monitor {
+        name: Some("DP-1".to_string()),
+        position: (0, 0),
+        size: (2560, 1440),
+        scale: 1.0,
+    };
+    assert!(s.layouts.placement(&[monitor], "incoming").is_some());
+    assert_eq!(backups(&cfg), vec!["config.json.v0.bak"]);
+}
+
+#[test]
+fn current_config_is_not_migrated() {
+    let (s, cfg) = load("v2.json");
+    assert_eq!(s.version, 2);
+    assert_eq!(s.positions["incoming"], (1480, 120));
+    assert_eq!(s.watcher.mode, WatcherMode::Notify);
+    assert!(backups(&cfg).is_empty());
+}
+
+#[test]
+fn snake_case_config_is_renamed() {
+    let (s, cfg) = load("v1.json");
+    assert_eq!(s.watcher.mode, WatcherMode::Notify);
+    assert_eq!(s.watcher.poll_interval_ms, 1000);
+    assert!(!s.overlay.always_on_top);
+    assert!(s.overlay.do_not_disturb);
+    let m = &s.macros[0];
+    assert_eq!(m.trade_type, TradeType::Incoming);
+    assert_eq!(m.steps[0].command, ChatCommand::TradeWith);
+    assert_eq!(m.steps[0].delay_ms, 250);
+    assert_eq!(m.steps[1].command, ChatCommand::Whisper("sent".to_string()));
+    assert_eq!(backups(&cfg), vec!["config.json.v1.bak"]);
+    assert_eq!(saved_version(&cfg), SETTINGS_VERSION as u64);
+}
+
+#[test]
+fn newer_config_is_only_read() {
+    let (s, cfg) = load("newer.json");
+    assert_eq!(s.version, 99);
+    assert_eq!(s.logpath, "/home/exile/Client.txt");
+    assert!(s.overlay.do_not_disturb);
+    // settings of the newer build stay in the file
+    assert!(backups(&cfg).is_empty());
+    assert_eq!(
+        s.save(cfg.to_str().unwrap())
+            .unwrap_err()
+            .downcast::<SettingsError>()
+            .unwrap(),
+        SettingsError::NewerVersion(99)
+    );
+    assert!(std::fs::read_to_string(&cfg).unwrap().contains("themes"));
+}
+
+#[test]
+fn invalid_values_are_repaired() {
+    let (s, cfg) = load("v0-invalid.json");
+    assert_eq!(s.logpath, "/home/exile/Client.txt");
+    assert_eq!(s.watcher.poll_interval_ms, MIN_POLL_INTERVAL_MS);
+    let names: Vec<&str> = s.macros.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, vec!["invite", "invite (2)", "macro 3"]);
+    assert_eq!(backups(&cfg), vec!["config.json.v0.bak"]);
+    assert!(Settings::new(cfg.to_str().unwrap())
+        .unwrap()
+        .validate()
+        .is_ok());
+}
+
+#[test]
+fn invalid_settings_are_not_saved() {
+    let (mut s, cfg) = load("v2.json");
+    let p = cfg.to_str().unwrap();
+    let before = std::fs::read(&cfg).unwrap();
+    s.watcher.poll_interval_ms = 10;
+    assert!(s.save(p).is_err());
+    s.watcher.poll_interval_ms = 500;
+    s.macros.push(s.macros[0].clone());
+    assert_eq!(
+        s.validate(),
+        Err(SettingsError::DuplicateMacro(s.macros[0].name.clone()))
+    );
+    assert!(s.save(p).is_err());
+    assert_eq!(std::fs::read(&cfg).unwrap(), before);
+
+    s.macros.pop();
+    s.save(p).unwrap();
+    assert_eq!(Settings::new(p).unwrap().watcher.poll_interval_ms, 500);
+    // temporary file was renamed over the config
+    assert!(!cfg.with_file_name("config.json.tmp").exists());
+}
+
+#[test]
+fn hand_edits_are_reported() {
+    let (s, cfg) = load("v2.json");
+    let p = cfg.to_str().unwrap().to_string();
+    let (tx, rx) = channel();
+    let _watcher = SettingsWatcher::new(&p, move || tx.send(()).unwrap()).unwrap();
+
+    let mut edited: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&cfg).unwrap()).unwrap();
+    edited["overlay"] = json!({ "doNotDisturb": true });
+    std::fs::write(&cfg, edited.to_string()).unwrap();
+    rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    let reloaded = Settings::new(&p).unwrap();
+    assert_eq!(reloaded.changes(&s), vec!["overlay"]);
+    assert!(reloaded.overlay.do_not_disturb);
+    // truncating and writing is reported once
+    assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+
+    // saves of the app show up as well but load unchanged
+    reloaded.save(&p).unwrap();
+    rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(Settings::new(&p).unwrap().changes(&reloaded).is_empty());
+}
@@ -1,20 +1,13 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod file_line_reader;
-mod model;
-mod settings;
-#[cfg(test)]
-mod test_utilities;
-
-use file_line_reader::FileLineReader;
+use command_queue::{CommandQueue, QueueHandle, SystemClock};
+use commands::TradeAction;
+use hotkeys::{HotkeyAction, HotkeyConflict, Hotkeys};
+use ingest::{IngestHandle, IngestStats, LogIngest};
 use log::{debug, error};
-use notify_debouncer_mini::{
-    new_debouncer_opt, notify::*, Config as NotifyDebouncerConfig, DebouncedEvent, Debouncer,
-};
+use log_watcher::{LogEvent, LogPathError, LogWatcher, WatchStatus, WatcherSettings};
 use std::{
-    fs::File,
-    path::Path,
     sync::{
         mpsc::{channel, Receiver, Sender},
         Arc, Mutex,
@@ -22,152 +15,638 @@ use std::{
     time::Duration,
 };
 use tauri::{
-    CustomMenuItem, Manager, PhysicalPosition, State, SystemTray, SystemTrayEvent, SystemTrayMenu,
+    CustomMenuItem, GlobalShortcutManager, Manager, PhysicalPosition, PhysicalSize, State,
+    SystemTray, SystemTrayEvent, SystemTrayMenu,
+};
+use trade_core::layout::{self, Placement};
+use trade_core::model_actor::{ModelEvent, ModelHandle, TradesSnapshot, Versioned};
+use trade_core::overlay::{Overlay, OverlaySettings, OverlayWindow};
+use trade_core::settings::SettingsWatcher;
+use trade_core::{
+    command_queue, commands, discovery, history, hotkeys, ingest, ipc, log_watcher, macros, model,
+    settings,
 };
 
 struct AppState {
     stx: Mutex<settings::Settings>,
     cfg_path: String,
-    file_line_reader: Mutex<Option<FileLineReader<File>>>,
-    model: Arc<Mutex<model::Model>>,
-    debouncer: Mutex<Debouncer<RecommendedWatcher>>,
+    _stx_watcher: Mutex<Option<SettingsWatcher>>,
+    ingest: IngestHandle,
+    model: ModelHandle,
+    watcher: Mutex<LogWatcher>,
+    overlay: Arc<Mutex<Overlay>>,
+    command_queue: QueueHandle<SystemClock>,
+    selected_trade: Mutex<Option<String>>,
 }
 
-fn subscribe_new_trades(
+// forwards model events to the overlay and the frontend, called from the model's
+// publisher thread
+fn publish_event(
     app: tauri::AppHandle,
-    model: Arc<Mutex<model::Model>>,
-    rx: Receiver<Result<Vec<DebouncedEvent>>>,
-) {
-    let apph = app.app_handle();
-    model.lock().unwrap().outgoing_subscribe(move |og| {
-        debug!("trigger new outgoing trade: {:?}", og);
-        apph.emit_all("new-outgoing-trade", og).unwrap();
-    });
-    let apph = app.app_handle();
-    model.lock().unwrap().incoming_subscribe(move |ig| {
-        debug!("trigger new incoming trade: {:?}", ig);
-        apph.emit_all("new-incoming-trade", ig).unwrap();
-    });
-
-    let apph = app.app_handle();
-    tauri::async_runtime::spawn(async move {
-        for res in rx {
-            match res {
-                Ok(_) => {
-                    debug!("start processing notify event");
-                    let appstate = apph.state::<AppState>();
-                    let mut flr = if let Ok(r) = appstate.file_line_reader.lock() {
-                        r
-                    } else {
-                        return;
-                    };
-                    if flr.is_none() {
-                        debug!("file_line_reader not initialized");
-                        return;
-                    }
-
-                    let r = flr.as_mut().unwrap().process_new_content();
-                    debug!("end processing notify event: {:?}", r);
-                }
-                Err(e) => panic!("file notify events fail: {:?}", e),
+    overlay: Arc<Mutex<Overlay>>,
+) -> impl Fn(Versioned<ModelEvent>) + Send + 'static {
+    move |ev| {
+        overlay.lock().unwrap().handle(&ev.data);
+        let version = ev.version;
+        let r = match ev.data {
+            ModelEvent::NewOutgoing(og) => {
+                debug!("trigger new outgoing trade: {:?}", og);
+                app.emit_all(ipc::NEW_OUTGOING_TRADE, Versioned { version, data: og })
             }
+            ModelEvent::NewIncoming(ig) => {
+                debug!("trigger new incoming trade: {:?}", ig);
+                app.emit_all(ipc::NEW_INCOMING_TRADE, Versioned { version, data: ig })
+            }
+            ModelEvent::IntentUpdated(intent) => {
+                debug!("trigger purchase intent update: {:?}", intent);
+                app.emit_all(
+                    ipc::OUTGOING_INTENT_UPDATED,
+                    Versioned {
+                        version,
+                        data: intent,
+                    },
+                )
+            }
+            ModelEvent::Closed(id) => {
+                debug!("trigger trade closed: {}", id);
+                app.emit_all(ipc::TRADE_CLOSED, Versioned { version, data: id })
+            }
+        };
+        if let Err(e) = r {
+            error!("can't emit model event: {}", e);
         }
-    });
+    }
 }
 
-fn init_config(
-    app: &mut tauri::App,
-    tx: Sender<Result<Vec<DebouncedEvent>>>,
-    model: Arc<Mutex<model::Model>>,
+// shows or hides an overlay window as the backend decided, they are never focused unless
+// the focus is wanted
+fn apply_overlay(
+    app: &tauri::AppHandle,
+    window: OverlayWindow,
+    shown: bool,
+    settings: &OverlaySettings,
 ) {
+    let w = match app.get_window(window.label()) {
+        Some(w) => w,
+        None => return,
+    };
+    let r = if shown {
+        w.set_always_on_top(settings.always_on_top)
+            .and_then(|_| w.set_ignore_cursor_events(settings.click_through))
+            .and_then(|_| w.show())
+            .and_then(|_| {
+                if settings.prevent_focus {
+                    Ok(())
+                } else {
+                    w.set_focus()
+                }
+            })
+    } else {
+        w.hide()
+    };
+    if let Err(e) = r {
+        error!("can't update {} window: {}", window.label(), e);
+    }
+}
+
+fn monitors(w: &tauri::Window) -> Vec<layout::Monitor> {
+    w.available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(|m| layout::Monitor {
+            name: m.name().cloned(),
+            position: (m.position().x, m.position().y),
+            size: (m.size().width, m.size().height),
+            scale: m.scale_factor(),
+        })
+        .collect()
+}
+
+// puts an overlay where it was saved for the current monitors, or at its old position,
+// and back onto a visible monitor if that is off-screen
+fn place_window(app: &tauri::App, stx: &settings::Settings, window: OverlayWindow) {
+    let w = app.get_window(window.label()).unwrap();
+    let monitors = monitors(&w);
+    let placement = match stx.layouts.placement(&monitors, window.label()) {
+        Some(p) => p,
+        None => {
+            let position = stx
+                .positions
+                .get(window.label())
+                .copied()
+                .unwrap_or_default();
+            let size = w
+                .inner_size()
+                .map(|s| (s.width, s.height))
+                .unwrap_or_default();
+            layout::clamp(&monitors, Placement { position, size })
+        }
+    };
+    debug!("placing {} window at {:?}", window.label(), placement);
+    let r = w
+        .set_size(PhysicalSize::new(placement.size.0, placement.size.1))
+        .and_then(|_| {
+            w.set_position(PhysicalPosition::new(
+                placement.position.0,
+                placement.position.1,
+            ))
+        });
+    if let Err(e) = r {
+        error!("can't place {} window: {}", window.label(), e);
+    }
+}
+
+const WATCH_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+fn init_config(app: &mut tauri::App, tx: Sender<LogEvent>, rx: Receiver<LogEvent>) {
     let base = app.path_resolver().app_config_dir().unwrap_or(
         app.path_resolver()
             .app_data_dir()
             .expect("can't get app data dir"),
     );
+    if let Err(e) = std::fs::create_dir_all(&base) {
+        error!("can't create config dir: {}", e);
+    }
     let app_data = base.join("config.json");
     let cfg_path = app_data.as_os_str().to_str().unwrap();
 
-    let stx = settings::Settings::new(cfg_path).unwrap_or(settings::Settings {
-        logpath: String::new(),
-        ..Default::default()
+    let mut stx = match settings::Settings::new(cfg_path) {
+        Ok(s) => s,
+        Err(e) => {
+            // keep a file that can't be read for the user instead of saving defaults over it
+            if app_data.exists() {
+                error!("can't load stx, starting with defaults: {:?}", e);
+                let broken = format!("{}.broken", cfg_path);
+                if let Err(e) = std::fs::copy(cfg_path, &broken) {
+                    error!("can't keep {}: {}", broken, e);
+                }
+            }
+            settings::Settings::default()
+        }
+    };
+    if stx.logpath.is_empty() {
+        // first run, take the most recently written log that could be found
+        if let Some(c) = discovery::discover().into_iter().next() {
+            debug!("discovered log: {:?}", c);
+            stx.logpath = c.path;
+            if let Err(e) = stx.save(cfg_path) {
+                error!("can't save stx: {}", e);
+            }
+        }
+    }
+
+    for window in OverlayWindow::ALL.iter() {
+        place_window(app, &stx, *window);
+    }
+
+    let mut watcher = match LogWatcher::new(tx.clone(), stx.watcher.clone()) {
+        Ok(w) => w,
+        Err(e) => {
+            error!("can't set up change notifications, polling the log: {}", e);
+            LogWatcher::polling_only(tx, stx.watcher.clone(), e.to_string())
+        }
+    };
+    let apph = app.app_handle();
+    watcher.status_subscribe(move |st| {
+        apph.emit_all(ipc::LOG_WATCHER_STATUS, st).unwrap();
     });
+    watcher.watch(&stx.logpath);
 
-    app.get_window("incoming")
-        .unwrap()
-        .set_position(PhysicalPosition::new(
-            stx.incoming_position.0,
-            stx.incoming_position.1,
-        ))
-        .expect("can't set incoming window position");
+    let mut overlay = Overlay::new(stx.overlay.clone());
+    let apph = app.app_handle();
+    overlay.window_subscribe(move |w, shown, s| apply_overlay(&apph, w, shown, s));
+    let overlay = Arc::new(Mutex::new(overlay));
 
-    app.get_window("outgoing")
-        .unwrap()
-        .set_position(PhysicalPosition::new(
-            stx.outgoing_position.0,
-            stx.outgoing_position.1,
-        ))
-        .expect("can't set outgoing window position");
-
-    let debouncer_config = NotifyDebouncerConfig::default()
-        .with_batch_mode(true)
-        .with_timeout(Duration::from_millis(300));
-    let mut debouncer: Debouncer<RecommendedWatcher> =
-        new_debouncer_opt(debouncer_config, tx).unwrap();
-    debouncer
-        .watcher()
-        .watch(Path::new(&stx.logpath), RecursiveMode::NonRecursive)
-        .unwrap();
-
-    let file_line_reader = FileLineReader::<File>::with_file(Arc::clone(&model), &stx.logpath).ok();
+    // events are published as soon as the model runs, backfilled trades included
+    let model = ModelHandle::spawn(
+        model::Model::new(),
+        publish_event(app.app_handle(), Arc::clone(&overlay)),
+    );
+    let handled = base.join("handled_trades.json");
+    model.send(move |m| m.set_handled(history::HandledTrades::load(handled)));
+    let apph = app.app_handle();
+    let ingest = IngestHandle::spawn(
+        LogIngest::new(model.clone(), &stx.logpath, &stx.backfill),
+        rx,
+        move |e| {
+            if let Some(appstate) = apph.try_state::<AppState>() {
+                appstate.watcher.lock().unwrap().fail(e);
+            }
+        },
+    );
+
+    let mut command_queue = CommandQueue::new(
+        stx.command_queue.clone(),
+        SystemClock,
+        commands::make_dispatcher(&stx.delivery),
+    );
+    let apph = app.app_handle();
+    command_queue.state_subscribe(move |st| {
+        apph.emit_all(ipc::COMMAND_QUEUE_STATE, st).unwrap();
+    });
+
+    let apph = app.app_handle();
+    let stx_watcher = match SettingsWatcher::new(cfg_path, move || reload_stx(&apph)) {
+        Ok(w) => Some(w),
+        Err(e) => {
+            error!(
+                "can't watch {}, edits are picked up on restart: {}",
+                cfg_path, e
+            );
+            None
+        }
+    };
 
     app.manage(AppState {
         stx: Mutex::new(stx),
         cfg_path: cfg_path.to_string(),
-        file_line_reader: Mutex::new(file_line_reader),
+        _stx_watcher: Mutex::new(stx_watcher),
+        ingest,
         model,
-        debouncer: Mutex::new(debouncer),
+        watcher: Mutex::new(watcher),
+        overlay,
+        command_queue: QueueHandle::spawn(command_queue),
+        selected_trade: Mutex::new(None),
+    });
+
+    let apph = app.app_handle();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(WATCH_RETRY_INTERVAL);
+        apph.state::<AppState>().watcher.lock().unwrap().retry();
     });
 }
 
+// changes that can't be kept, e.g. of a file written by a newer build, are reported
+// to the settings page instead of being lost silently
+fn save_stx(app: &tauri::AppHandle, s: &settings::Settings, cfg_path: &str) {
+    if let Err(e) = s.save(cfg_path) {
+        error!("can't save stx: {}", e);
+        let _ = app.emit_all(ipc::SETTINGS_SAVE_FAILED, e.to_string());
+    }
+}
+
+// saves where an overlay is after it was moved or resized, for the current monitors
 #[tauri::command]
-fn update_position_stx(stx: State<AppState>, position: (i32, i32), window: String) {
+fn save_window_layout(app: tauri::AppHandle, stx: State<AppState>, window: String) {
+    let w = match app.get_window(&window) {
+        Some(w) => w,
+        None => return,
+    };
+    let placement = match (w.outer_position(), w.inner_size()) {
+        (Ok(p), Ok(s)) => Placement {
+            position: (p.x, p.y),
+            size: (s.width, s.height),
+        },
+        _ => return,
+    };
     let mut s = stx.stx.lock().unwrap();
-    if window == "incoming" {
-        s.incoming_position = position;
-    } else {
-        s.outgoing_position = position;
-    }
-    let r = s.save(&stx.cfg_path);
-    if r.is_err() {
-        error!("can't save stx: {}", r.unwrap_err());
-    }
-    debug!("called update_position_stx {:?} {}", position, window);
+    s.layouts.save(&monitors(&w), &window, &placement);
+    save_stx(&app, &s, &stx.cfg_path);
+    debug!("called save_window_layout {} {:?}", window, placement);
 }
 
 #[tauri::command]
-fn update_logpath_stx(stx: State<AppState>, logpath: String) {
+fn update_logpath_stx(
+    app: tauri::AppHandle,
+    stx: State<AppState>,
+    logpath: String,
+) -> Result<(), LogPathError> {
+    log_watcher::validate_log(&logpath)?;
     let mut s = stx.stx.lock().unwrap();
-    let oldpath = std::mem::replace(&mut s.logpath, logpath);
-    let r = s.save(&stx.cfg_path);
-    if r.is_err() {
-        error!("can't save stx: {}", r.unwrap_err());
-    }
+    s.logpath = logpath;
+    save_stx(&app, &s, &stx.cfg_path);
     debug!("called update_logpath_stx {}", s.logpath);
-    let file_line_reader =
-        FileLineReader::<File>::with_file(Arc::clone(&stx.model), &s.logpath).ok();
-    *stx.file_line_reader.lock().unwrap() = file_line_reader;
-    let mut db = stx.debouncer.lock().unwrap();
-    db.watcher().unwatch(Path::new(&oldpath)).unwrap();
-    db.watcher()
-        .watch(Path::new(&s.logpath), RecursiveMode::NonRecursive)
-        .unwrap();
+    stx.ingest.with(|i| i.set_log(&s.logpath, &s.backfill));
+    stx.watcher.lock().unwrap().watch(&s.logpath);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_watcher_stx(stx: State<AppState>) -> WatcherSettings {
+    stx.stx.lock().unwrap().watcher.clone()
+}
+
+// switches between notifications and polling without restarting
+#[tauri::command]
+fn update_watcher_stx(app: tauri::AppHandle, stx: State<AppState>, watcher: WatcherSettings) {
+    let mut s = stx.stx.lock().unwrap();
+    s.watcher = watcher;
+    save_stx(&app, &s, &stx.cfg_path);
+    debug!("called update_watcher_stx {:?}", s.watcher);
+    stx.watcher.lock().unwrap().set_settings(s.watcher.clone());
+}
+
+#[tauri::command]
+fn get_overlay_stx(stx: State<AppState>) -> OverlaySettings {
+    stx.stx.lock().unwrap().overlay.clone()
+}
+
+#[tauri::command]
+fn update_overlay_stx(app: tauri::AppHandle, overlay: OverlaySettings) {
+    set_overlay_stx(&app, overlay);
+}
+
+// saves and applies overlay settings changed on the settings page or in the tray
+fn set_overlay_stx(app: &tauri::AppHandle, overlay: OverlaySettings) {
+    let appstate = app.state::<AppState>();
+    let mut s = appstate.stx.lock().unwrap();
+    s.overlay = overlay;
+    save_stx(app, &s, &appstate.cfg_path);
+    debug!("called set_overlay_stx {:?}", s.overlay);
+    apply_overlay_stx(app, &s.overlay);
+}
+
+// brings the tray, the overlay windows and the settings page in line with the settings
+fn apply_overlay_stx(app: &tauri::AppHandle, overlay: &OverlaySettings) {
+    let r = app
+        .tray_handle()
+        .get_item("do_not_disturb")
+        .set_selected(overlay.do_not_disturb);
+    if let Err(e) = r {
+        error!("can't update tray: {}", e);
+    }
+    app.state::<AppState>()
+        .overlay
+        .lock()
+        .unwrap()
+        .set_settings(overlay.clone());
+    app.emit_all(ipc::OVERLAY_SETTINGS, overlay).unwrap();
+}
+
+// applies config.json after it was edited outside the app, the app's own saves load
+// unchanged and are skipped
+fn reload_stx(app: &tauri::AppHandle) {
+    let appstate = match app.try_state::<AppState>() {
+        Some(s) => s,
+        None => return,
+    };
+    // saves are made under this lock too, so the file read here is never older than stx;
+    // it's released before applying, registering hotkeys and updating the tray wait for
+    // the main thread which may be waiting for this lock
+    let (changes, s) = {
+        let mut s = appstate.stx.lock().unwrap();
+        let loaded = match settings::Settings::new(&appstate.cfg_path) {
+            Ok(l) => l,
+            Err(e) => {
+                error!("can't reload stx, keeping current: {:?}", e);
+                return;
+            }
+        };
+        let changes = loaded.changes(&s);
+        if changes.is_empty() {
+            return;
+        }
+        debug!("reloaded stx, changed {:?}", changes);
+        *s = loaded;
+        (changes, s.clone())
+    };
+
+    if changes.contains(&"logpath") {
+        appstate.ingest.with(|i| i.set_log(&s.logpath, &s.backfill));
+        appstate.watcher.lock().unwrap().watch(&s.logpath);
+    }
+    if changes.contains(&"watcher") {
+        appstate.watcher.lock().unwrap().set_settings(s.watcher);
+    }
+    if changes.contains(&"overlay") {
+        apply_overlay_stx(app, &s.overlay);
+    }
+    if changes.contains(&"hotkeys") {
+        register_hotkeys(app, &s.hotkeys);
+    }
+    // macros are read when used, layouts, command queue and delivery apply on next start
+    app.emit_all(ipc::SETTINGS_CHANGED, &changes).unwrap();
+}
+
+#[tauri::command]
+fn get_log_watcher_status(stx: State<AppState>) -> WatchStatus {
+    stx.watcher.lock().unwrap().status()
+}
+
+// time from log write to trade events being sent, with reader restarts
+#[tauri::command]
+fn get_ingest_stats(stx: State<AppState>) -> IngestStats {
+    stx.ingest.with(|i| i.stats())
+}
+
+// existing Client.txt files, the most recently written first
+#[tauri::command]
+fn discover_logpaths() -> Vec<discovery::Candidate> {
+    discovery::discover()
+}
+
+// current trades for windows that (re)loaded and missed events
+#[tauri::command]
+fn get_trades(stx: State<AppState>) -> Result<TradesSnapshot, String> {
+    stx.model
+        .snapshot()
+        .ok_or_else(|| "model is not running".to_string())
 }
 
 #[tauri::command]
 fn trade_close(stx: State<AppState>, id: String) {
-    let mut m = stx.model.lock().unwrap();
-    m.remove_trade(id);
+    stx.command_queue.with(|q| q.cancel_group(&id));
+    stx.model.send(move |m| m.remove_trade(id));
+}
+
+#[tauri::command]
+fn trade_complete(stx: State<AppState>, id: String) {
+    let trade = id.clone();
+    let closed = stx
+        .model
+        .call(move |m| m.complete_trade(&trade))
+        .unwrap_or_default();
+    stx.command_queue.with(|q| {
+        q.cancel_group(&id);
+        for other in closed.iter() {
+            q.cancel_group(other);
+        }
+    });
+}
+
+#[tauri::command]
+fn trade_decline(stx: State<AppState>, id: String) -> Option<model::PurchaseIntent> {
+    stx.command_queue.with(|q| q.cancel_group(&id));
+    stx.model.call(move |m| m.decline_trade(&id)).flatten()
+}
+
+// cancels queued chat commands of a trade or all of them
+#[tauri::command]
+fn cancel_commands(stx: State<AppState>, id: Option<String>) -> usize {
+    match id {
+        Some(id) => stx.command_queue.with(|q| q.cancel_group(&id)),
+        None => stx.command_queue.with(|q| q.cancel_all()),
+    }
+}
+
+#[tauri::command]
+fn get_command_queue_state(stx: State<AppState>) -> command_queue::QueueState {
+    stx.command_queue.with(|q| q.state())
+}
+
+#[tauri::command]
+fn list_macros(stx: State<AppState>, trade_type: model::TradeType) -> Vec<macros::ChatMacro> {
+    let s = stx.stx.lock().unwrap();
+    macros::for_trade_type(&s.macros, &trade_type)
+        .cloned()
+        .collect()
+}
+
+#[tauri::command]
+fn run_macro(stx: State<AppState>, id: String, name: String) -> Result<(), String> {
+    let chat_macro = stx
+        .stx
+        .lock()
+        .unwrap()
+        .macros
+        .iter()
+        .find(|m| m.name == name)
+        .cloned()
+        .ok_or(format!("unknown macro: {}", name))?;
+    let trade = id.clone();
+    let steps = stx
+        .model
+        .call(move |m| {
+            m.get_trade(&trade).map(|t| {
+                if chat_macro.applies_to(t) {
+                    Ok(chat_macro.render(t))
+                } else {
+                    Err(format!(
+                        "macro {} is not for {:?} trades",
+                        chat_macro.name,
+                        t.trade_type()
+                    ))
+                }
+            })
+        })
+        .flatten()
+        .ok_or(format!("unknown trade: {}", id))??;
+    debug!("called run_macro {} for trade {}", name, id);
+    stx.command_queue.with(|q| q.push_steps(steps, Some(id)));
+    Ok(())
+}
+
+fn perform_trade_action(
+    app: &tauri::AppHandle,
+    id: &str,
+    action: TradeAction,
+) -> Result<(), String> {
+    let appstate = app.state::<AppState>();
+    let trade = id.to_string();
+    let steps = appstate
+        .model
+        .call(move |m| m.get_trade(&trade).map(|t| action.steps(t)))
+        .flatten()
+        .ok_or(format!("unknown trade: {}", id))?;
+    debug!("perform trade action {:?} for trade {}", action, id);
+    appstate
+        .command_queue
+        .with(|q| q.push_steps(steps, Some(id.to_string())));
+    Ok(())
+}
+
+#[tauri::command]
+fn trade_action(app: tauri::AppHandle, id: String, action: TradeAction) -> Result<(), String> {
+    perform_trade_action(&app, &id, action)
+}
+
+#[tauri::command]
+fn select_trade(stx: State<AppState>, id: String) {
+    *stx.selected_trade.lock().unwrap() = Some(id);
+}
+
+// selected incoming trade if it is still open, otherwise the oldest one
+fn hotkey_target(app: &tauri::AppHandle) -> Option<String> {
+    let appstate = app.state::<AppState>();
+    let selected = appstate.selected_trade.lock().unwrap().clone();
+    appstate
+        .model
+        .call(move |m| {
+            m.trade_or_oldest(selected.as_deref(), &model::TradeType::Incoming)
+                .map(|t| t.id().to_string())
+        })
+        .flatten()
+}
+
+fn handle_hotkey(app: &tauri::AppHandle, action: HotkeyAction) {
+    let appstate = app.state::<AppState>();
+    let target = hotkey_target(app);
+    debug!("hotkey {:?} pressed, target trade {:?}", action, target);
+    match action {
+        HotkeyAction::Trade(action) => {
+            if let Some(id) = target {
+                if let Err(e) = perform_trade_action(app, &id, action) {
+                    error!("can't perform hotkey action: {}", e);
+                }
+            }
+        }
+        HotkeyAction::Close => {
+            if let Some(id) = target {
+                appstate.command_queue.with(|q| q.cancel_group(&id));
+                appstate.model.send(move |m| m.remove_trade(id));
+            }
+        }
+        HotkeyAction::Cycle => {
+            let next = appstate
+                .model
+                .call(move |m| {
+                    m.next_trade(target.as_deref(), &model::TradeType::Incoming)
+                        .map(|t| t.id().to_string())
+                })
+                .flatten();
+            if let Some(id) = next {
+                *appstate.selected_trade.lock().unwrap() = Some(id.clone());
+                app.emit_all(ipc::INCOMING_TRADE_SELECTED, id).unwrap();
+            }
+        }
+    }
+}
+
+// (re)registers global shortcuts and reports the ones that failed
+fn register_hotkeys(app: &tauri::AppHandle, hotkeys: &Hotkeys) -> Vec<HotkeyConflict> {
+    let mut manager = app.global_shortcut_manager();
+    if let Err(e) = manager.unregister_all() {
+        error!("can't unregister global shortcuts: {}", e);
+    }
+
+    let mut conflicts = hotkeys.duplicates();
+    for (accelerator, action) in hotkeys.bindings() {
+        if conflicts
+            .iter()
+            .any(|c| c.accelerator.eq_ignore_ascii_case(accelerator))
+        {
+            continue;
+        }
+        let apph = app.app_handle();
+        if let Err(e) = manager.register(accelerator, move || handle_hotkey(&apph, action)) {
+            error!("can't register global shortcut {}: {}", accelerator, e);
+            conflicts.push(HotkeyConflict {
+                accelerator: accelerator.to_string(),
+                reason: e.to_string(),
+            });
+        }
+    }
+
+    app.emit_all(ipc::HOTKEY_CONFLICTS, &conflicts).unwrap();
+    conflicts
+}
+
+#[tauri::command]
+fn get_hotkeys_stx(stx: State<AppState>) -> Hotkeys {
+    stx.stx.lock().unwrap().hotkeys.clone()
+}
+
+#[tauri::command]
+fn update_hotkeys_stx(
+    app: tauri::AppHandle,
+    stx: State<AppState>,
+    hotkeys: Hotkeys,
+) -> Vec<HotkeyConflict> {
+    let mut s = stx.stx.lock().unwrap();
+    s.hotkeys = hotkeys;
+    save_stx(&app, &s, &stx.cfg_path);
+    debug!("called update_hotkeys_stx {:?}", s.hotkeys);
+    register_hotkeys(&app, &s.hotkeys)
 }
 
 fn system_tray_event_handler(app: &tauri::AppHandle, event: SystemTrayEvent) {
@@ -180,6 +659,11 @@ fn system_tray_event_handler(app: &tauri::AppHandle, event: SystemTrayEvent) {
                 let window = app.get_window("settings").unwrap();
                 window.show().unwrap();
             }
+            "do_not_disturb" => {
+                let mut overlay = app.state::<AppState>().stx.lock().unwrap().overlay.clone();
+                overlay.do_not_disturb = !overlay.do_not_disturb;
+                set_overlay_stx(app, overlay);
+            }
             _ => {}
         },
         _ => {}
@@ -189,24 +673,55 @@ fn system_tray_event_handler(app: &tauri::AppHandle, event: SystemTrayEvent) {
 fn main() {
     let (tx, rx) = channel();
 
-    let model = Arc::new(Mutex::new(model::Model::new()));
-
     let quit = CustomMenuItem::new("quit".to_string(), "Quit");
     let settings = CustomMenuItem::new("settings".to_string(), "Settings");
-    let tray_menu = SystemTrayMenu::new().add_item(quit).add_item(settings);
+    let do_not_disturb = CustomMenuItem::new("do_not_disturb".to_string(), "Do not disturb");
+    let tray_menu = SystemTrayMenu::new()
+        .add_item(quit)
+        .add_item(settings)
+        .add_item(do_not_disturb);
     let tray = SystemTray::new().with_menu(tray_menu);
 
     tauri::Builder::default()
         .plugin(tauri_plugin_log::Builder::default().build())
         .setup(move |app| {
-            init_config(app, tx, Arc::clone(&model));
-            subscribe_new_trades(app.app_handle(), model, rx);
+            init_config(app, tx, rx);
+            let dnd = app
+                .state::<AppState>()
+                .stx
+                .lock()
+                .unwrap()
+                .overlay
+                .do_not_disturb;
+            app.tray_handle()
+                .get_item("do_not_disturb")
+                .set_selected(dnd)?;
+            let hotkeys = app.state::<AppState>().stx.lock().unwrap().hotkeys.clone();
+            register_hotkeys(&app.app_handle(), &hotkeys);
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
-            update_position_stx,
+            save_window_layout,
             update_logpath_stx,
+            discover_logpaths,
+            get_log_watcher_status,
+            get_ingest_stats,
+            get_watcher_stx,
+            update_watcher_stx,
+            get_overlay_stx,
+            update_overlay_stx,
+            get_trades,
             trade_close,
+            trade_complete,
+            trade_decline,
+            list_macros,
+            run_macro,
+            trade_action,
+            select_trade,
+            get_hotkeys_stx,
+            update_hotkeys_stx,
+            cancel_commands,
+            get_command_queue_state,
         ])
         .system_tray(tray)
         .on_system_tray_event(system_tray_event_handler)
@@ -0,0 +1,109 @@
+// generated from src-tauri/core/src/ipc.rs, do not edit; regenerate with
+// UPDATE_IPC=1 cargo test -p trade-core --test ipc
+import { invoke as tauriInvoke } from '@tauri-apps/api/tauri';
+import { emit as tauriEmit, listen as tauriListen } from '@tauri-apps/api/event';
+import type { EventCallback, UnlistenFn } from '@tauri-apps/api/event';
+
+export const IPC_VERSION = 6;
+
+export type TradeType = "incoming" | "outgoing";
+
+export type TradeStatus = "active" | "declined" | "offline" | "afk" | "failed";
+
+export type FollowUpIntent = { "kind": "counterOffer", costNumber: string | null, costCurrency: string | null, } | { "kind": "cancel" } | { "kind": "waiting" } | { "kind": "sold" } | { "kind": "question" };
+
+export type TradeInfo = { id: string, type: TradeType, itemName: string, playerName: string, time: string, lastMessage: string, league: string, item2Name: string | null, costNumber: string | null, costCurrency: string | null, stash: string | null, left: string | null, top: string | null, status: TradeStatus, statusReason: string | null, followUp: FollowUpIntent | null, };
+
+export type PurchaseIntent = { itemName: string, league: string, sellers: Array<string>, suggested: string | null, };
+
+export type Versioned<T> = { version: number, data: T, };
+
+export type TradesSnapshot = { version: number, incoming: Array<TradeInfo>, outgoing: Array<TradeInfo>, intents: Array<PurchaseIntent>, };
+
+export type QueueState = { pending: number, sent: number, failed: number, cancelled: number, next: string | null, };
+
+export type ChatCommand = "invite" | "kick" | "tradeWith" | "hideout" | { "whisper": string } | { "raw": string };
+
+export type TradeAction = "invite" | "trade" | "kick" | "thank" | "hideout" | "askToWait" | "stillInterested" | "soldAlready";
+
+export type MacroStep = { command: ChatCommand, delayMs: number, };
+
+export type ChatMacro = { name: string, tradeType: TradeType, steps: Array<MacroStep>, };
+
+export type Hotkeys = { invite: string | null, trade: string | null, kick: string | null, thank: string | null, close: string | null, cycle: string | null, };
+
+export type HotkeyConflict = { accelerator: string, reason: string, };
+
+export type CandidateSource = "standalone" | "steam" | "proton" | "wine";
+
+export type Candidate = { path: string, source: CandidateSource, modified: number | null, };
+
+export type WatcherMode = "auto" | "notify" | "poll";
+
+export type WatcherSettings = { mode: WatcherMode, pollIntervalMs: number, };
+
+export type WatchState = "idle" | "watching" | "failed";
+
+export type WatchStatus = { state: WatchState, path: string, error: string | null, polling: boolean, };
+
+export type IngestStats = { batches: number, lines: number, restarts: number, lastMs: number | null, p50Ms: number | null, p95Ms: number | null, maxMs: number | null, };
+
+export type OverlaySettings = { alwaysOnTop: boolean, clickThrough: boolean, preventFocus: boolean, doNotDisturb: boolean, };
+
+export type LogPathError = { kind: "empty" | "notFound" | "notAFile" | "unreadable" | "notClientLog", message: string, };
+
+export type Commands = {
+	save_window_layout: { args: { window: string }; result: null; error: never };
+	update_logpath_stx: { args: { logpath: string }; result: null; error: LogPathError };
+	discover_logpaths: { args: Record<string, never>; result: Array<Candidate>; error: never };
+	get_log_watcher_status: { args: Record<string, never>; result: WatchStatus; error: never };
+	get_ingest_stats: { args: Record<string, never>; result: IngestStats; error: never };
+	get_watcher_stx: { args: Record<string, never>; result: WatcherSettings; error: never };
+	update_watcher_stx: { args: { watcher: WatcherSettings }; result: null; error: never };
+	get_overlay_stx: { args: Record<string, never>; result: OverlaySettings; error: never };
+	update_overlay_stx: { args: { overlay: OverlaySettings }; result: null; error: never };
+	get_trades: { args: Record<string, never>; result: TradesSnapshot; error: string };
+	trade_close: { args: { id: string }; result: null; error: never };
+	trade_complete: { args: { id: string }; result: null; error: never };
+	trade_decline: { args: { id: string }; result: PurchaseIntent | null; error: never };
+	list_macros: { args: { tradeType: TradeType }; result: Array<ChatMacro>; error: never };
+	run_macro: { args: { id: string; name: string }; result: null; error: string };
+	trade_action: { args: { id: string; action: TradeAction }; result: null; error: string };
+	select_trade: { args: { id: string }; result: null; error: never };
+	get_hotkeys_stx: { args: Record<string, never>; result: Hotkeys; error: never };
+	update_hotkeys_stx: { args: { hotkeys: Hotkeys }; result: Array<HotkeyConflict>; error: never };
+	cancel_commands: { args: { id: string | null }; result: number; error: never };
+	get_command_queue_state: { args: Record<string, never>; result: QueueState; error: never };
+};
+
+export type Events = {
+	'new-incoming-trade': Versioned<TradeInfo>;
+	'new-outgoing-trade': Versioned<TradeInfo>;
+	'outgoing-intent-updated': Versioned<PurchaseIntent>;
+	'trade-closed': Versioned<string>;
+	'incoming-trade-selected': string;
+	'command-queue-state': QueueState;
+	'hotkey-conflicts': Array<HotkeyConflict>;
+	'log-watcher-status': WatchStatus;
+	'overlay-settings': OverlaySettings;
+	'settings-changed': Array<string>;
+	'settings-save-failed': string;
+};
+
+export function invoke<C extends keyof Commands>(
+	cmd: C,
+	args?: Commands[C]['args']
+): Promise<Commands[C]['result']> {
+	return tauriInvoke(cmd, args);
+}
+
+export function listen<E extends keyof Events>(
+	event: E,
+	handler: EventCallback<Events[E]>
+): Promise<UnlistenFn> {
+	return tauriListen(event, handler);
+}
+
+export function emit<E extends keyof Events>(event: E, payload?: Events[E]): Promise<void> {
+	return tauriEmit(event, payload);
+}
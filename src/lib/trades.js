@@ -0,0 +1,61 @@
+import { invoke, listen } from '$lib/ipc';
+
+const EVENTS = [
+	['new-incoming-trade', 'incoming'],
+	['new-outgoing-trade', 'outgoing'],
+	['outgoing-intent-updated', 'intent'],
+	['trade-closed', 'closed']
+];
+
+// keeps a window in sync with the model: events are applied in version order, a
+// window that was (re)loaded or missed an event starts over from a get_trades snapshot.
+// handlers: snapshot, incoming, outgoing, intent, closed; returns unlisten function
+export async function syncTrades(handlers) {
+	let version = null;
+	let syncing = false;
+	let queued = [];
+
+	async function resync() {
+		if (syncing) {
+			return;
+		}
+		syncing = true;
+		try {
+			const snapshot = await invoke('get_trades');
+			version = snapshot.version;
+			handlers.snapshot?.(snapshot);
+		} catch (e) {
+			console.error('can not get trades', e);
+		} finally {
+			syncing = false;
+		}
+		const pending = queued;
+		queued = [];
+		pending.forEach(apply);
+	}
+
+	function apply(ev) {
+		if (syncing) {
+			queued.push(ev);
+			return;
+		}
+		const { version: v, data } = ev.payload;
+		if (version !== null && v <= version) {
+			// already part of the snapshot
+			return;
+		}
+		if (version === null || v !== version + 1) {
+			queued.push(ev);
+			resync();
+			return;
+		}
+		version = v;
+		handlers[ev.kind]?.(data);
+	}
+
+	const unlisteners = await Promise.all(
+		EVENTS.map(([name, kind]) => listen(name, (ev) => apply({ kind, payload: ev.payload })))
+	);
+	await resync();
+	return () => unlisteners.forEach((u) => u());
+}